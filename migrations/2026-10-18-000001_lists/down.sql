@@ -0,0 +1,2 @@
+drop table list_notes;
+drop table lists;
@@ -0,0 +1,15 @@
+CREATE TABLE lists (
+  id SERIAL PRIMARY KEY,
+  uuid VARCHAR NOT NULL UNIQUE,
+  title VARCHAR NOT NULL,
+  owner VARCHAR NOT NULL,
+  description TEXT NOT NULL
+);
+
+-- Notes in a list are ordered, so we key them by their position in the list
+CREATE TABLE list_notes (
+  list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  note_uuid VARCHAR NOT NULL,
+  PRIMARY KEY (list_id, position)
+);
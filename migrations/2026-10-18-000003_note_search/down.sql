@@ -0,0 +1,2 @@
+drop index notes_search;
+alter table notes drop column search;
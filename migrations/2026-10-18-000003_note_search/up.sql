@@ -0,0 +1,7 @@
+-- Titles are weighted above descriptions when ranking search results
+ALTER TABLE notes ADD COLUMN search tsvector GENERATED ALWAYS AS (
+  setweight(to_tsvector('english', title), 'A') ||
+  setweight(to_tsvector('english', description), 'B')
+) STORED;
+
+CREATE INDEX notes_search ON notes USING GIN (search);
@@ -0,0 +1,5 @@
+-- One index per way of sorting a page of notes, see PsqlNoteStore::get_notes.
+-- Keys compare bytewise, like they do in the other backends
+CREATE INDEX notes_owner_create_time ON notes (owner, create_time COLLATE "C", uuid COLLATE "C");
+CREATE INDEX notes_owner_last_update_time ON notes (owner, last_update_time COLLATE "C", uuid COLLATE "C");
+CREATE INDEX notes_owner_title ON notes (owner, title COLLATE "C", uuid COLLATE "C");
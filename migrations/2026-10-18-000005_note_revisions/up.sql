@@ -0,0 +1,10 @@
+-- Every past version of a note, numbered from 1 for each note
+CREATE TABLE note_revisions (
+  note_id INTEGER NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+  revision INTEGER NOT NULL,
+  title VARCHAR NOT NULL,
+  description TEXT NOT NULL,
+  tags TEXT [] NOT NULL,
+  last_update_time VARCHAR NOT NULL,
+  PRIMARY KEY (note_id, revision)
+);
@@ -0,0 +1,2 @@
+-- Goes up by one with every change to a note, so concurrent edits can be detected
+ALTER TABLE notes ADD COLUMN version BIGINT NOT NULL DEFAULT 1;
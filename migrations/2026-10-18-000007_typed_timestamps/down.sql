@@ -0,0 +1,12 @@
+drop index notes_owner_create_time;
+drop index notes_owner_last_update_time;
+
+alter table notes
+  alter column create_time type varchar using extract(epoch from create_time)::bigint::varchar,
+  alter column last_update_time type varchar using extract(epoch from last_update_time)::bigint::varchar,
+  alter column delete_time type varchar using extract(epoch from delete_time)::bigint::varchar;
+alter table note_revisions
+  alter column last_update_time type varchar using extract(epoch from last_update_time)::bigint::varchar;
+
+create index notes_owner_create_time on notes (owner, create_time collate "C", uuid collate "C");
+create index notes_owner_last_update_time on notes (owner, last_update_time collate "C", uuid collate "C");
@@ -0,0 +1,14 @@
+-- Timestamps used to be strings of unix seconds
+-- The indexes compare the old strings bytewise, so they are rebuilt around the new columns
+DROP INDEX notes_owner_create_time;
+DROP INDEX notes_owner_last_update_time;
+
+ALTER TABLE notes
+  ALTER COLUMN create_time TYPE TIMESTAMPTZ USING to_timestamp(create_time::BIGINT),
+  ALTER COLUMN last_update_time TYPE TIMESTAMPTZ USING to_timestamp(last_update_time::BIGINT),
+  ALTER COLUMN delete_time TYPE TIMESTAMPTZ USING to_timestamp(delete_time::BIGINT);
+ALTER TABLE note_revisions
+  ALTER COLUMN last_update_time TYPE TIMESTAMPTZ USING to_timestamp(last_update_time::BIGINT);
+
+CREATE INDEX notes_owner_create_time ON notes (owner, create_time, uuid COLLATE "C");
+CREATE INDEX notes_owner_last_update_time ON notes (owner, last_update_time, uuid COLLATE "C");
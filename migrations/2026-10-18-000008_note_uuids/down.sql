@@ -0,0 +1,11 @@
+drop index notes_uuid;
+drop index notes_owner;
+drop index notes_owner_create_time;
+drop index notes_owner_last_update_time;
+drop index notes_owner_title;
+
+alter table notes alter column uuid type varchar using uuid::varchar;
+
+create index notes_owner_create_time on notes (owner, create_time, uuid collate "C");
+create index notes_owner_last_update_time on notes (owner, last_update_time, uuid collate "C");
+create index notes_owner_title on notes (owner, title collate "C", uuid collate "C");
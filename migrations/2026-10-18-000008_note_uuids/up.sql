@@ -0,0 +1,12 @@
+-- The page indexes end in uuid, and collations don't apply to UUIDs, which compare bytewise
+DROP INDEX notes_owner_create_time;
+DROP INDEX notes_owner_last_update_time;
+DROP INDEX notes_owner_title;
+
+ALTER TABLE notes ALTER COLUMN uuid TYPE UUID USING uuid::UUID;
+
+CREATE UNIQUE INDEX notes_uuid ON notes (uuid);
+CREATE INDEX notes_owner ON notes (owner);
+CREATE INDEX notes_owner_create_time ON notes (owner, create_time, uuid);
+CREATE INDEX notes_owner_last_update_time ON notes (owner, last_update_time, uuid);
+CREATE INDEX notes_owner_title ON notes (owner, title COLLATE "C", uuid);
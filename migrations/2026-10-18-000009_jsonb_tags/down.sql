@@ -0,0 +1,2 @@
+alter table notes alter column tags type text[] using tags::text[];
+alter table note_revisions alter column tags type text[] using tags::text[];
@@ -0,0 +1,3 @@
+-- Each tag was already stored as a JSON document, it just wasn't typed as one
+ALTER TABLE notes ALTER COLUMN tags TYPE JSONB[] USING tags::JSONB[];
+ALTER TABLE note_revisions ALTER COLUMN tags TYPE JSONB[] USING tags::JSONB[];
@@ -0,0 +1,14 @@
+alter table notes add column tags jsonb[] not null default '{}';
+
+update notes set tags = grouped.tags
+from (
+  select note_id,
+    array_agg(case when value is null then to_jsonb(kind) else jsonb_build_object(kind, value) end) as tags
+  from note_tags
+  group by note_id
+) as grouped
+where notes.id = grouped.note_id;
+
+alter table notes alter column tags drop default;
+
+drop table note_tags;
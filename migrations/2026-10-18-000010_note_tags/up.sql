@@ -0,0 +1,24 @@
+-- Each tag gets a row, split into its variant and payload, e.g. RecommendedBy and Alice
+CREATE TABLE note_tags (
+  note_id INTEGER NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+  kind VARCHAR NOT NULL,
+  -- NULL for tags without a payload, like Book
+  value VARCHAR
+);
+
+-- Tags are stored as "Kind" or {"Kind": "payload"}
+INSERT INTO note_tags (note_id, kind, value)
+SELECT DISTINCT notes.id, parts.kind, parts.value
+FROM notes, unnest(notes.tags) AS tag,
+  LATERAL (
+    SELECT tag #>> '{}' AS kind, NULL AS value WHERE jsonb_typeof(tag) = 'string'
+    UNION ALL
+    SELECT key, value FROM jsonb_each_text(tag) WHERE jsonb_typeof(tag) = 'object'
+  ) AS parts;
+
+ALTER TABLE notes DROP COLUMN tags;
+
+-- A note has each tag once. NULLs are never equal to each other, hence the COALESCE
+CREATE UNIQUE INDEX note_tags_note ON note_tags (note_id, kind, COALESCE(value, ''));
+-- Finding the notes with a tag, e.g. every note RecommendedBy Alice, or counting Book notes
+CREATE INDEX note_tags_kind_value ON note_tags (kind, value, note_id);
@@ -0,0 +1,8 @@
+-- Tags an owner made up, used by their notes as Custom(name)
+CREATE TABLE tag_definitions (
+  owner VARCHAR NOT NULL,
+  name VARCHAR NOT NULL,
+  description TEXT NOT NULL,
+  colour VARCHAR NOT NULL,
+  PRIMARY KEY (owner, name)
+);
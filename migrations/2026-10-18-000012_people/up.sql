@@ -0,0 +1,10 @@
+-- People whose names turn up in the RecommendedBy and RemindsMeOf tags of notes
+CREATE TABLE people (
+  uuid UUID PRIMARY KEY,
+  owner VARCHAR NOT NULL,
+  name VARCHAR NOT NULL,
+  aliases VARCHAR[] NOT NULL,
+  description TEXT NOT NULL
+);
+
+CREATE INDEX people_owner_name ON people (owner, name);
@@ -0,0 +1,2 @@
+-- Where a note is in its owner's workflow, e.g. Wishlist or Done
+ALTER TABLE notes ADD COLUMN status VARCHAR NOT NULL DEFAULT 'Wishlist';
@@ -15,3 +15,6 @@ cargo build
 
 # Run tests
 cargo test
+# notes_lib is its own package, so its tests need to be run separately.
+# Set NOTES_TEST_DATABASE_URL to also run the storage tests against Postgres
+cargo test --manifest-path src/notes_lib/Cargo.toml --features sqlite
@@ -1,11 +1,14 @@
 use anyhow::{Context, Result};
 use clap::arg_enum;
 use env_logger::Env;
-use log::info;
+use log::{error, info};
 use notes_lib::routes::build_warp_routes;
 use notes_lib::service::RequestHandler;
-use notes_lib::storage::{MemoryNoteStore, PsqlNoteStore};
+use notes_lib::storage::{FileNoteStore, MemoryNoteStore, NoteStore, PsqlNoteStore};
+use notes_lib::timestamp::{self, TimestampFormat};
 use std::env;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use structopt::StructOpt;
 use tokio_compat_02::FutureExt;
 
@@ -14,6 +17,8 @@ arg_enum! {
     pub enum Storage {
     Psql,
         Memory,
+        Sqlite,
+        File,
     }
 }
 
@@ -23,7 +28,7 @@ arg_enum! {
     about = "An App for keeping track of my thoughts in a semi-structued way"
 )]
 struct Args {
-    /// Database URL to connect to
+    /// Database URL to connect to. For sqlite, this is the path of the database file
     #[structopt(long, env)]
     database_url: String,
     /// Make the logging loud and annoying
@@ -34,6 +39,94 @@ struct Args {
     port: u16,
     #[structopt(long, possible_values = &Storage::variants(), case_insensitive = true, default_value="psql")]
     storage_type: Storage,
+    /// Directory the file storage keeps its notes in
+    #[structopt(long, default_value = "data", parse(from_os_str))]
+    data_dir: PathBuf,
+    /// Keep memory storage in a snapshot file at this path, so notes survive a restart
+    #[structopt(long, parse(from_os_str))]
+    snapshot_path: Option<PathBuf>,
+    /// Seconds between snapshots of memory storage
+    #[structopt(long, default_value = "60", parse(try_from_str = parse_interval))]
+    snapshot_interval: u64,
+    /// Permanently delete notes that have been archived for more than this many days.
+    /// Archived notes are kept forever if this isn't given
+    #[structopt(long)]
+    archive_retention_days: Option<u64>,
+    /// Seconds between purges of expired archived notes
+    #[structopt(long, default_value = "3600", parse(try_from_str = parse_interval))]
+    purge_interval: u64,
+    /// Only log which archived notes would be purged, without deleting them
+    #[structopt(long)]
+    purge_dry_run: bool,
+    /// Write note timestamps as strings of unix seconds, like before, for clients that expect them
+    #[structopt(long)]
+    legacy_timestamps: bool,
+}
+
+/// Parse a number of seconds between two runs of something, which can't be 0
+fn parse_interval(seconds: &str) -> Result<u64, String> {
+    match seconds.parse::<u64>() {
+        Ok(0) => Err("The interval has to be at least a second".to_string()),
+        Ok(seconds) => Ok(seconds),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Periodically purge notes that were archived longer ago than `--archive-retention-days`
+fn start_archive_purge<S: NoteStore>(args: &Args, mut note_store: S) -> Result<()> {
+    let retention_days = match args.archive_retention_days {
+        Some(retention_days) => retention_days,
+        None => return Ok(()),
+    };
+    let retention = retention_days
+        .checked_mul(24 * 60 * 60)
+        .context("--archive-retention-days is too large")?;
+    let dry_run = args.purge_dry_run;
+    info!(
+        "Purging notes archived more than {} days ago{}",
+        retention_days,
+        if dry_run { " (dry run)" } else { "" }
+    );
+    let mut interval = tokio::time::interval(Duration::from_secs(args.purge_interval));
+    tokio::spawn(async move {
+        loop {
+            interval.tick().await;
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("The clock is before 1970");
+            let cutoff = now.as_secs().saturating_sub(retention);
+            let cutoff = timestamp::from_epoch_seconds(cutoff as i64);
+            match note_store.purge_archived_before(cutoff, dry_run) {
+                Ok(notes) => {
+                    for note in &notes {
+                        info!(
+                            "{} note {} \"{}\" of {}, archived at {}",
+                            if dry_run { "Would purge" } else { "Purged" },
+                            note.uuid,
+                            note.title,
+                            note.owner,
+                            note.delete_time
+                                .map(|time| time.to_rfc3339())
+                                .unwrap_or_default()
+                        );
+                    }
+                }
+                Err(err) => error!("Could not purge archived notes: {:#}", err),
+            }
+        }
+    });
+    Ok(())
+}
+
+/// Resolves once we're asked to stop, either by ctrl-c or a SIGTERM
+async fn shutdown_signal() {
+    let mut terminate = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        .expect("Could not listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate.recv() => {},
+    }
+    info!("Shutting down");
 }
 
 #[tokio::main]
@@ -57,6 +150,11 @@ async fn main() -> Result<()> {
         }
         Err(..) => args.port,
     };
+    let timestamp_format = if args.legacy_timestamps {
+        TimestampFormat::EpochSeconds
+    } else {
+        TimestampFormat::Rfc3339
+    };
 
     // Run the service. Because we can't return different types, and we can't make
     // things trait objects either, we run the code in a weird way.
@@ -65,18 +163,91 @@ async fn main() -> Result<()> {
         Storage::Psql => {
             info!("Connecting to database at url: {}", args.database_url);
             let note_store = PsqlNoteStore::new(&args.database_url);
+            start_archive_purge(&args, note_store.clone())?;
             let handler = RequestHandler::new(note_store);
-            let routes = build_warp_routes(handler);
+            let routes = build_warp_routes(handler, timestamp_format);
             info!("Running server on port {}", port);
             warp::serve(routes).run(([0, 0, 0, 0], port)).compat().await;
         }
         Storage::Memory => {
-            info!("Using Memory Storage. Note, no notes will be saved!");
-            let handler = RequestHandler::new(MemoryNoteStore::new());
-            let routes = build_warp_routes(handler);
+            let note_store = match &args.snapshot_path {
+                Some(snapshot_path) => {
+                    info!(
+                        "Using Memory Storage, snapshotted to {}",
+                        snapshot_path.display()
+                    );
+                    MemoryNoteStore::load_snapshot(snapshot_path).context("Loading snapshot")?
+                }
+                None => {
+                    info!("Using Memory Storage. Note, no notes will be saved!");
+                    MemoryNoteStore::new()
+                }
+            };
+            start_archive_purge(&args, note_store.clone())?;
+            let handler = RequestHandler::new(note_store.clone());
+            let routes = build_warp_routes(handler, timestamp_format);
+            info!("Running server on port {}", port);
+            match args.snapshot_path {
+                Some(snapshot_path) => {
+                    let snapshotter = note_store.clone();
+                    let path = snapshot_path.clone();
+                    let mut interval =
+                        tokio::time::interval(Duration::from_secs(args.snapshot_interval));
+                    let snapshots = tokio::spawn(async move {
+                        // The first tick completes immediately, and there is nothing new to save yet
+                        interval.tick().await;
+                        loop {
+                            interval.tick().await;
+                            if let Err(err) = snapshotter.dump_snapshot(&path) {
+                                error!("Could not write snapshot: {:#}", err);
+                            }
+                        }
+                    });
+                    // warp binds as soon as it's asked to, so this needs to happen inside of compat
+                    async move {
+                        let (_, server) = warp::serve(routes)
+                            .bind_with_graceful_shutdown(([0, 0, 0, 0], port), shutdown_signal());
+                        server.await
+                    }
+                    .compat()
+                    .await;
+                    // Both write through the same temporary file, so let a snapshot that's
+                    // already being written finish before writing the final one
+                    snapshots.abort();
+                    let _ = snapshots.await;
+                    info!("Writing final snapshot to {}", snapshot_path.display());
+                    note_store
+                        .dump_snapshot(&snapshot_path)
+                        .context("Writing final snapshot")?;
+                }
+                None => warp::serve(routes).run(([0, 0, 0, 0], port)).compat().await,
+            }
+        }
+        Storage::File => {
+            info!("Using file storage in {}", args.data_dir.display());
+            let note_store = FileNoteStore::new(&args.data_dir);
+            start_archive_purge(&args, note_store.clone())?;
+            let handler = RequestHandler::new(note_store);
+            let routes = build_warp_routes(handler, timestamp_format);
             info!("Running server on port {}", port);
             warp::serve(routes).run(([0, 0, 0, 0], port)).compat().await;
         }
+        #[cfg(feature = "sqlite")]
+        Storage::Sqlite => {
+            info!("Using sqlite database at {}", args.database_url);
+            let note_store = notes_lib::storage::SqliteNoteStore::new(&args.database_url);
+            start_archive_purge(&args, note_store.clone())?;
+            let handler = RequestHandler::new(note_store);
+            let routes = build_warp_routes(handler, timestamp_format);
+            info!("Running server on port {}", port);
+            warp::serve(routes).run(([0, 0, 0, 0], port)).compat().await;
+        }
+        #[cfg(not(feature = "sqlite"))]
+        Storage::Sqlite => {
+            anyhow::bail!(
+                "This build doesn't support sqlite storage. Rebuild with `--features sqlite`"
+            );
+        }
     };
     Ok(())
 }
@@ -0,0 +1,4 @@
+drop table list_notes;
+drop table lists;
+drop table note_tags;
+drop table notes;
@@ -0,0 +1,35 @@
+CREATE TABLE notes (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  uuid TEXT NOT NULL UNIQUE,
+  title TEXT NOT NULL,
+  owner TEXT NOT NULL,
+  description TEXT NOT NULL,
+  create_time TEXT NOT NULL,
+  last_update_time TEXT NOT NULL,
+  delete_time TEXT
+);
+
+CREATE INDEX notes_owner ON notes (owner);
+
+-- SQLite has no arrays, so each tag gets a row. Tags are serialized the same
+-- way they are in the Postgres tags array
+CREATE TABLE note_tags (
+  note_id INTEGER NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+  tag TEXT NOT NULL,
+  PRIMARY KEY (note_id, tag)
+);
+
+CREATE TABLE lists (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  uuid TEXT NOT NULL UNIQUE,
+  title TEXT NOT NULL,
+  owner TEXT NOT NULL,
+  description TEXT NOT NULL
+);
+
+CREATE TABLE list_notes (
+  list_id INTEGER NOT NULL REFERENCES lists (id) ON DELETE CASCADE,
+  position INTEGER NOT NULL,
+  note_uuid TEXT NOT NULL,
+  PRIMARY KEY (list_id, position)
+);
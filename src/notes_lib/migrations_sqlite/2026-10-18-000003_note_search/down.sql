@@ -0,0 +1,4 @@
+drop trigger notes_fts_update;
+drop trigger notes_fts_delete;
+drop trigger notes_fts_insert;
+drop table notes_fts;
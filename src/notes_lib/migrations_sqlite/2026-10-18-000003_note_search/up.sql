@@ -0,0 +1,24 @@
+-- Full text index over notes, kept in sync by triggers. Titles are weighted
+-- above descriptions when ranking, see SqliteNoteStore::search_notes
+CREATE VIRTUAL TABLE notes_fts USING fts5(
+  title,
+  description,
+  content = 'notes',
+  content_rowid = 'id',
+  tokenize = 'porter unicode61'
+);
+
+INSERT INTO notes_fts (rowid, title, description) SELECT id, title, description FROM notes;
+
+CREATE TRIGGER notes_fts_insert AFTER INSERT ON notes BEGIN
+  INSERT INTO notes_fts (rowid, title, description) VALUES (new.id, new.title, new.description);
+END;
+
+CREATE TRIGGER notes_fts_delete AFTER DELETE ON notes BEGIN
+  INSERT INTO notes_fts (notes_fts, rowid, title, description) VALUES ('delete', old.id, old.title, old.description);
+END;
+
+CREATE TRIGGER notes_fts_update AFTER UPDATE ON notes BEGIN
+  INSERT INTO notes_fts (notes_fts, rowid, title, description) VALUES ('delete', old.id, old.title, old.description);
+  INSERT INTO notes_fts (rowid, title, description) VALUES (new.id, new.title, new.description);
+END;
@@ -0,0 +1,3 @@
+drop index notes_owner_create_time;
+drop index notes_owner_last_update_time;
+drop index notes_owner_title;
@@ -0,0 +1,4 @@
+-- One index per way of sorting a page of notes, see SqliteNoteStore::get_notes
+CREATE INDEX notes_owner_create_time ON notes (owner, create_time, uuid);
+CREATE INDEX notes_owner_last_update_time ON notes (owner, last_update_time, uuid);
+CREATE INDEX notes_owner_title ON notes (owner, title, uuid);
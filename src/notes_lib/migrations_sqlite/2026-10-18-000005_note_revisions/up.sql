@@ -0,0 +1,11 @@
+-- Every past version of a note, numbered from 1 for each note.
+-- Tags are kept as a JSON array, as they are only ever read back whole
+CREATE TABLE note_revisions (
+  note_id INTEGER NOT NULL REFERENCES notes (id) ON DELETE CASCADE,
+  revision INTEGER NOT NULL,
+  title TEXT NOT NULL,
+  description TEXT NOT NULL,
+  tags TEXT NOT NULL,
+  last_update_time TEXT NOT NULL,
+  PRIMARY KEY (note_id, revision)
+);
@@ -0,0 +1,6 @@
+UPDATE notes SET
+  create_time = strftime('%s', create_time),
+  last_update_time = strftime('%s', last_update_time),
+  delete_time = strftime('%s', delete_time);
+UPDATE note_revisions SET
+  last_update_time = strftime('%s', last_update_time);
@@ -0,0 +1,8 @@
+-- Timestamps used to be strings of unix seconds. They are now written the way
+-- diesel writes a Timestamp, which sorts the same as the time it holds
+UPDATE notes SET
+  create_time = strftime('%Y-%m-%d %H:%M:%S', create_time, 'unixepoch'),
+  last_update_time = strftime('%Y-%m-%d %H:%M:%S', last_update_time, 'unixepoch'),
+  delete_time = strftime('%Y-%m-%d %H:%M:%S', delete_time, 'unixepoch');
+UPDATE note_revisions SET
+  last_update_time = strftime('%Y-%m-%d %H:%M:%S', last_update_time, 'unixepoch');
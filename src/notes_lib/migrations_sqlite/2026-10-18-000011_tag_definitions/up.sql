@@ -0,0 +1,8 @@
+-- Tags an owner made up, used by their notes as Custom(name)
+CREATE TABLE tag_definitions (
+  owner TEXT NOT NULL,
+  name TEXT NOT NULL,
+  description TEXT NOT NULL,
+  colour TEXT NOT NULL,
+  PRIMARY KEY (owner, name)
+);
@@ -0,0 +1,11 @@
+-- People whose names turn up in the RecommendedBy and RemindsMeOf tags of notes.
+-- Aliases are a JSON array of strings
+CREATE TABLE people (
+  uuid TEXT PRIMARY KEY NOT NULL,
+  owner TEXT NOT NULL,
+  name TEXT NOT NULL,
+  aliases TEXT NOT NULL,
+  description TEXT NOT NULL
+);
+
+CREATE INDEX people_owner_name ON people (owner, name);
@@ -1,4 +1,4 @@
-use crate::types::{GetNotesRequest, GetNotesResponse};
+use crate::types::{ArchivedNotes, GetNotesQuery, GetNotesRequest, GetNotesResponse};
 use anyhow::Result;
 use async_trait::async_trait;
 
@@ -21,7 +21,26 @@ pub struct NotesClient {
 #[async_trait]
 impl Client for NotesClient {
     async fn get_notes(&self, request: GetNotesRequest) -> Result<GetNotesResponse> {
-        let resp = reqwest::get(format!("{}/notes/{}", self.endpoint, request.owner)).await?;
+        // The trash has an endpoint of its own
+        let path = match request.archived {
+            ArchivedNotes::Only => format!("{}/notes/{}/archived", self.endpoint, request.owner),
+            _ => format!("{}/notes/{}", self.endpoint, request.owner),
+        };
+        let query = GetNotesQuery {
+            include_archived: Some(request.archived == ArchivedNotes::Include),
+            status: request.status,
+            tags: request.tags.map(|tags| tags.to_string()),
+            sort: Some(request.sort),
+            since: request.since,
+            until: request.until,
+            limit: request.limit,
+            cursor: request.cursor,
+        };
+        let resp = reqwest::Client::new()
+            .get(path)
+            .query(&query)
+            .send()
+            .await?;
 
         println!("{:?}", resp);
         let resp = resp.json::<GetNotesResponse>().await;
@@ -0,0 +1,62 @@
+use diesel::r2d2::PoolError;
+use std::fmt;
+
+pub type Result<T, E = NotesError> = std::result::Result<T, E>;
+
+/// Errors surfaced by the `NotesService` and the `NoteStore` backends.
+/// Each variant maps onto an HTTP status in the routes.
+#[derive(Debug)]
+pub enum NotesError {
+    /// The requested note or list doesn't exist
+    NotFound(String),
+    /// The request can't be fulfilled as given
+    InvalidInput(String),
+    /// The request conflicts with what is currently stored
+    Conflict(String),
+    /// The storage backend couldn't be reached
+    StorageUnavailable(anyhow::Error),
+    /// Anything unexpected, most likely a bug or a failed query
+    Internal(anyhow::Error),
+}
+
+impl NotesError {
+    /// Short, stable name for this kind of error. Used as the `error` field of responses
+    pub fn kind(&self) -> &'static str {
+        match self {
+            NotesError::NotFound(_) => "not_found",
+            NotesError::InvalidInput(_) => "invalid_input",
+            NotesError::Conflict(_) => "conflict",
+            NotesError::StorageUnavailable(_) => "storage_unavailable",
+            NotesError::Internal(_) => "internal",
+        }
+    }
+}
+
+impl fmt::Display for NotesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NotesError::NotFound(message) => write!(f, "Not found: {}", message),
+            NotesError::InvalidInput(message) => write!(f, "Invalid input: {}", message),
+            NotesError::Conflict(message) => write!(f, "Conflict: {}", message),
+            NotesError::StorageUnavailable(err) => write!(f, "Storage unavailable: {:#}", err),
+            NotesError::Internal(err) => write!(f, "Internal error: {:#}", err),
+        }
+    }
+}
+
+impl std::error::Error for NotesError {}
+
+// Anything we haven't explicitly classified is an internal error. This keeps
+// `.context()` and `?` usable inside of the storage backends. A `NotesError`
+// passed through anyhow, e.g. to roll back a transaction, comes back out as itself.
+impl From<anyhow::Error> for NotesError {
+    fn from(err: anyhow::Error) -> Self {
+        err.downcast().unwrap_or_else(NotesError::Internal)
+    }
+}
+
+impl From<PoolError> for NotesError {
+    fn from(err: PoolError) -> Self {
+        NotesError::StorageUnavailable(err.into())
+    }
+}
@@ -0,0 +1,113 @@
+//! Comparing versions of a note
+use crate::types::{DiffLine, FieldChange, NoteDiff, NoteRevision, Tag};
+
+/// What changed going from version `from` of a note to version `to`
+pub fn diff(from: &NoteRevision, to: &NoteRevision) -> NoteDiff {
+    let title = if from.title == to.title {
+        None
+    } else {
+        Some(FieldChange {
+            from: from.title.clone(),
+            to: to.title.clone(),
+        })
+    };
+    NoteDiff {
+        title,
+        description: diff_lines(&from.description, &to.description),
+        tags_added: sorted(to.tags.difference(&from.tags).cloned().collect()),
+        tags_removed: sorted(from.tags.difference(&to.tags).cloned().collect()),
+    }
+}
+
+/// Tags have no order of their own, so sort them by how they read
+fn sorted(mut tags: Vec<Tag>) -> Vec<Tag> {
+    tags.sort_by_key(|tag| format!("{:?}", tag));
+    tags
+}
+
+/// Largest table `diff_lines` builds, in entries. Past this the changed lines are
+/// shown as removed and added wholesale, rather than allocating gigabytes for them
+const MAX_DIFF_CELLS: usize = 1_000_000;
+
+/// A line by line diff that keeps as many lines as it can
+fn diff_lines(from: &str, to: &str) -> Vec<DiffLine> {
+    let from: Vec<&str> = from.lines().collect();
+    let to: Vec<&str> = to.lines().collect();
+    // Lines the two have in common at the start and the end need no table
+    let prefix = from
+        .iter()
+        .zip(&to)
+        .take_while(|(from, to)| from == to)
+        .count();
+    let suffix = from[prefix..]
+        .iter()
+        .rev()
+        .zip(to[prefix..].iter().rev())
+        .take_while(|(from, to)| from == to)
+        .count();
+    let same = |lines: &[&str]| {
+        lines
+            .iter()
+            .map(|line| DiffLine::Same(line.to_string()))
+            .collect::<Vec<DiffLine>>()
+    };
+
+    let mut lines = same(&from[..prefix]);
+    let changed_from = &from[prefix..from.len() - suffix];
+    let changed_to = &to[prefix..to.len() - suffix];
+    let cells = (changed_from.len() + 1).saturating_mul(changed_to.len() + 1);
+    if cells > MAX_DIFF_CELLS {
+        lines.extend(
+            changed_from
+                .iter()
+                .map(|line| DiffLine::Removed(line.to_string())),
+        );
+        lines.extend(
+            changed_to
+                .iter()
+                .map(|line| DiffLine::Added(line.to_string())),
+        );
+    } else {
+        lines.extend(common_subsequence_diff(changed_from, changed_to));
+    }
+    lines.extend(same(&from[from.len() - suffix..]));
+    lines
+}
+
+/// Diff two lists of lines through their longest common subsequence
+fn common_subsequence_diff(from: &[&str], to: &[&str]) -> Vec<DiffLine> {
+    // common[i][j] is the length of the longest common subsequence of from[i..] and to[j..]
+    let mut common = vec![vec![0usize; to.len() + 1]; from.len() + 1];
+    for i in (0..from.len()).rev() {
+        for j in (0..to.len()).rev() {
+            common[i][j] = if from[i] == to[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < from.len() && j < to.len() {
+        if from[i] == to[j] {
+            lines.push(DiffLine::Same(from[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if common[i + 1][j] >= common[i][j + 1] {
+            lines.push(DiffLine::Removed(from[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(to[j].to_string()));
+            j += 1;
+        }
+    }
+    lines.extend(
+        from[i..]
+            .iter()
+            .map(|line| DiffLine::Removed(line.to_string())),
+    );
+    lines.extend(to[j..].iter().map(|line| DiffLine::Added(line.to_string())));
+    lines
+}
@@ -1,8 +1,15 @@
 #[macro_use]
 extern crate diesel;
+#[cfg(feature = "sqlite")]
+#[macro_use]
+extern crate diesel_migrations;
 
 pub mod client;
+pub mod error;
+pub mod history;
 pub mod routes;
 pub mod service;
 pub mod storage;
+pub mod tag_query;
+pub mod timestamp;
 pub mod types;
@@ -1,44 +1,97 @@
+use crate::error::NotesError;
 use crate::service::{NotesService, RequestHandler};
 use crate::storage::NoteStore;
+use crate::timestamp::{self, TimestampFormat};
 use crate::types::{
-    ArchiveNoteRequest, CreateNoteRequest, GetNoteRequest, GetNotesRequest, UpdateNoteRequest,
+    ArchiveNoteRequest, ArchivedNotes, CreateListRequest, CreateNoteRequest, CreatePersonRequest,
+    DefineTagBody, DefineTagRequest, DeleteListRequest, DiffNoteRequest, DiffQuery, ErrorResponse,
+    GetFollowupsRequest, GetFullListRequest, GetListsRequest, GetNoteHistoryRequest,
+    GetNoteRequest, GetNotesQuery, GetNotesRequest, GetPeopleRequest, GetRecommendationsRequest,
+    GetTagDefinitionsRequest, GetTagFacetsRequest, GetTagTreeRequest, PurgeNoteRequest,
+    RenameTagBody, RenameTagRequest, RevertNoteRequest, SearchNotesRequest, SearchQuery, TagQuery,
+    UnarchiveNoteRequest, UpdateListRequest, UpdateNoteRequest, UpdatePersonRequest,
 };
-use log::info;
+use log::{error, info};
+use serde::Serialize;
+use std::convert::Infallible;
 use uuid::Uuid;
-use warp::{filters::BoxedFilter, http, Filter, Reply};
+use warp::{filters::BoxedFilter, http, reject, Filter, Rejection, Reply};
+
+impl reject::Reject for NotesError {}
 
 async fn create_note<S: NoteStore>(
     request: CreateNoteRequest,
     mut handler: RequestHandler<S>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let response = handler
-        .create_note(request)
-        .expect("Should be able to create note");
+    let response = handler.create_note(request).map_err(warp::reject::custom)?;
     Ok(warp::reply::with_status(
         format!("Uuid: {}", response.note_id),
         http::StatusCode::CREATED,
     ))
 }
 
+/// Reply with `response` as JSON, writing its timestamps in `format`
+fn json<T: Serialize>(response: &T, format: TimestampFormat) -> warp::reply::Json {
+    timestamp::with_format(format, || warp::reply::json(response))
+}
+
+/// The ETag of a note at `version`
+fn etag(version: u64) -> String {
+    format!("\"{}\"", version)
+}
+
+/// The version of a note an `If-Match` header asks for. `*` matches any version
+fn expected_version(if_match: &str) -> Result<Option<u64>, NotesError> {
+    let if_match = if_match.trim();
+    if if_match == "*" {
+        return Ok(None);
+    }
+    if_match
+        .strip_prefix('"')
+        .and_then(|tag| tag.strip_suffix('"'))
+        .and_then(|version| version.parse::<u64>().ok())
+        .map(Some)
+        .ok_or_else(|| {
+            NotesError::InvalidInput(format!("If-Match {} is not the ETag of a note", if_match))
+        })
+}
+
 async fn get_note<S: NoteStore>(
     uuid: Uuid,
+    format: TimestampFormat,
     handler: RequestHandler<S>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let response = handler
         .get_note(GetNoteRequest { note_id: uuid })
-        .expect("Should be able to get note");
-    Ok(warp::reply::json(&response))
+        .map_err(warp::reject::custom)?;
+    let etag = etag(response.note.version);
+    Ok(warp::reply::with_header(
+        json(&response, format),
+        "ETag",
+        etag,
+    ))
 }
 
 async fn update_note<S: NoteStore>(
     uuid: Uuid,
-    update_note_request: UpdateNoteRequest,
+    mut update_note_request: UpdateNoteRequest,
+    if_match: Option<String>,
     mut handler: RequestHandler<S>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     info!("Updating {}", uuid);
+    if update_note_request.note_id != uuid {
+        return Err(reject::custom(NotesError::InvalidInput(format!(
+            "Path id {} does not match note_id {}",
+            uuid, update_note_request.note_id
+        ))));
+    }
+    if let Some(if_match) = if_match {
+        update_note_request.expected_version =
+            expected_version(&if_match).map_err(warp::reject::custom)?;
+    }
     let response = handler
         .update_note(update_note_request)
-        .expect("Should be able to update note");
+        .map_err(warp::reject::custom)?;
     Ok(warp::reply::json(&response))
 }
 
@@ -49,29 +102,403 @@ async fn archive_note<S: NoteStore>(
     info!("Archiving {}", uuid);
     let response = handler
         .archive_note(ArchiveNoteRequest { note_id: uuid })
-        .expect("Should be able to archive note");
+        .map_err(warp::reject::custom)?;
     Ok(warp::reply::json(&response))
 }
 
+async fn unarchive_note<S: NoteStore>(
+    uuid: Uuid,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("Unarchiving {}", uuid);
+    let response = handler
+        .unarchive_note(UnarchiveNoteRequest { note_id: uuid })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn purge_note<S: NoteStore>(
+    uuid: Uuid,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("Purging {}", uuid);
+    let response = handler
+        .purge_note(PurgeNoteRequest { note_id: uuid })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn get_note_history<S: NoteStore>(
+    uuid: Uuid,
+    format: TimestampFormat,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_note_history(GetNoteHistoryRequest { note_id: uuid })
+        .map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+async fn diff_note<S: NoteStore>(
+    uuid: Uuid,
+    query: DiffQuery,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .diff_note(DiffNoteRequest {
+            note_id: uuid,
+            from: query.from,
+            to: query.to,
+        })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn revert_note<S: NoteStore>(
+    uuid: Uuid,
+    revision: u32,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("Reverting {} to revision {}", uuid, revision);
+    let response = handler
+        .revert_note(RevertNoteRequest {
+            note_id: uuid,
+            revision,
+        })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+fn get_notes_request(
+    owner: String,
+    query: GetNotesQuery,
+    archived: ArchivedNotes,
+) -> Result<GetNotesRequest, warp::Rejection> {
+    let tags = match query.tags {
+        Some(tags) => Some(tags.parse::<TagQuery>().map_err(warp::reject::custom)?),
+        None => None,
+    };
+    Ok(GetNotesRequest {
+        owner,
+        archived,
+        status: query.status,
+        tags,
+        sort: query.sort.unwrap_or_default(),
+        since: query.since,
+        until: query.until,
+        limit: query.limit,
+        cursor: query.cursor,
+    })
+}
+
 async fn get_notes<S: NoteStore>(
+    owner: String,
+    query: GetNotesQuery,
+    format: TimestampFormat,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let archived = match query.include_archived {
+        Some(true) => ArchivedNotes::Include,
+        _ => ArchivedNotes::Exclude,
+    };
+    let request = get_notes_request(owner, query, archived)?;
+    let response = handler.get_notes(request).map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+async fn get_archived_notes<S: NoteStore>(
+    owner: String,
+    query: GetNotesQuery,
+    format: TimestampFormat,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let request = get_notes_request(owner, query, ArchivedNotes::Only)?;
+    let response = handler.get_notes(request).map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+async fn search_notes<S: NoteStore>(
+    owner: String,
+    query: SearchQuery,
+    format: TimestampFormat,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .search_notes(SearchNotesRequest {
+            owner,
+            query: query.q,
+        })
+        .map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+async fn get_tag_facets<S: NoteStore>(
+    owner: String,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_tag_facets(GetTagFacetsRequest { owner })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn define_tag<S: NoteStore>(
+    owner: String,
+    body: DefineTagBody,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("Defining tag {} for {}", body.name, owner);
+    handler
+        .define_tag(DefineTagRequest {
+            owner,
+            name: body.name,
+            description: body.description,
+            colour: body.colour,
+        })
+        .map_err(warp::reject::custom)?;
+    Ok(http::StatusCode::CREATED)
+}
+
+async fn get_tag_definitions<S: NoteStore>(
+    owner: String,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_tag_definitions(GetTagDefinitionsRequest { owner })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn rename_tag<S: NoteStore>(
+    owner: String,
+    body: RenameTagBody,
+    format: TimestampFormat,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!(
+        "Renaming tag {:?} to {:?} for {}{}",
+        body.from,
+        body.to,
+        owner,
+        if body.dry_run { " (dry run)" } else { "" }
+    );
+    let response = handler
+        .rename_tag(RenameTagRequest {
+            owner,
+            from: body.from,
+            to: body.to,
+            dry_run: body.dry_run,
+        })
+        .map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+async fn get_tag_tree<S: NoteStore>(
+    owner: String,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_tag_tree(GetTagTreeRequest { owner })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn create_list<S: NoteStore>(
+    request: CreateListRequest,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler.create_list(request).map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        format!("Uuid: {}", response.list_id),
+        http::StatusCode::CREATED,
+    ))
+}
+
+async fn get_lists<S: NoteStore>(
+    owner: String,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_lists(GetListsRequest { owner })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn get_full_list<S: NoteStore>(
+    uuid: Uuid,
+    format: TimestampFormat,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_full_list(GetFullListRequest { list_id: uuid })
+        .map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+async fn update_list<S: NoteStore>(
+    uuid: Uuid,
+    update_list_request: UpdateListRequest,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("Updating list {}", uuid);
+    if update_list_request.list_id != uuid {
+        return Err(reject::custom(NotesError::InvalidInput(format!(
+            "Path id {} does not match list_id {}",
+            uuid, update_list_request.list_id
+        ))));
+    }
+    let response = handler
+        .update_list(update_list_request)
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn delete_list<S: NoteStore>(
+    uuid: Uuid,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("Deleting list {}", uuid);
+    let response = handler
+        .delete_list(DeleteListRequest { list_id: uuid })
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn create_person<S: NoteStore>(
+    request: CreatePersonRequest,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .create_person(request)
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::with_status(
+        format!("Uuid: {}", response.person_id),
+        http::StatusCode::CREATED,
+    ))
+}
+
+async fn get_people<S: NoteStore>(
     owner: String,
     handler: RequestHandler<S>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     let response = handler
-        .get_notes(GetNotesRequest { owner })
-        .expect("Should find notes for this owner");
+        .get_people(GetPeopleRequest { owner })
+        .map_err(warp::reject::custom)?;
     Ok(warp::reply::json(&response))
 }
 
-/// This function links the service to warp's route handling
-pub fn build_warp_routes<S: NoteStore>(handler: RequestHandler<S>) -> BoxedFilter<(impl Reply,)> {
+async fn update_person<S: NoteStore>(
+    uuid: Uuid,
+    update_person_request: UpdatePersonRequest,
+    mut handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    info!("Updating person {}", uuid);
+    if update_person_request.person_id != uuid {
+        return Err(reject::custom(NotesError::InvalidInput(format!(
+            "Path id {} does not match person_id {}",
+            uuid, update_person_request.person_id
+        ))));
+    }
+    let response = handler
+        .update_person(update_person_request)
+        .map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
+
+async fn get_recommendations<S: NoteStore>(
+    uuid: Uuid,
+    format: TimestampFormat,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_recommendations(GetRecommendationsRequest { person_id: uuid })
+        .map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+async fn get_followups<S: NoteStore>(
+    owner: String,
+    format: TimestampFormat,
+    handler: RequestHandler<S>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = handler
+        .get_followups(GetFollowupsRequest { owner })
+        .map_err(warp::reject::custom)?;
+    Ok(json(&response, format))
+}
+
+/// Turns any rejection into a JSON `ErrorResponse` with a matching status code
+async fn handle_rejection(err: Rejection) -> Result<impl warp::Reply, Infallible> {
+    let (status, kind, message) = if let Some(notes_error) = err.find::<NotesError>() {
+        let status = match notes_error {
+            NotesError::NotFound(_) => http::StatusCode::NOT_FOUND,
+            NotesError::InvalidInput(_) => http::StatusCode::BAD_REQUEST,
+            NotesError::Conflict(_) => http::StatusCode::CONFLICT,
+            NotesError::StorageUnavailable(_) => http::StatusCode::SERVICE_UNAVAILABLE,
+            NotesError::Internal(_) => http::StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        (status, notes_error.kind(), notes_error.to_string())
+    } else if let Some(body_error) = err.find::<warp::filters::body::BodyDeserializeError>() {
+        (
+            http::StatusCode::BAD_REQUEST,
+            "invalid_input",
+            body_error.to_string(),
+        )
+    } else if let Some(query_error) = err.find::<reject::InvalidQuery>() {
+        (
+            http::StatusCode::BAD_REQUEST,
+            "invalid_input",
+            query_error.to_string(),
+        )
+    } else if err.find::<reject::PayloadTooLarge>().is_some() {
+        (
+            http::StatusCode::PAYLOAD_TOO_LARGE,
+            "invalid_input",
+            "Request body is too large".to_string(),
+        )
+    } else if err.is_not_found() || err.find::<reject::MethodNotAllowed>().is_some() {
+        // Our routes check the method before the path, so warp reports a
+        // MethodNotAllowed for any path it couldn't match on another method.
+        // A request that did reach its route is rejected by the other routes too,
+        // so this has to come after the checks above, or a bad body would be a 404.
+        (
+            http::StatusCode::NOT_FOUND,
+            "not_found",
+            "No such route".to_string(),
+        )
+    } else {
+        (
+            http::StatusCode::INTERNAL_SERVER_ERROR,
+            "internal",
+            format!("Unhandled rejection: {:?}", err),
+        )
+    };
+    if status.is_server_error() {
+        error!("{}", message);
+    }
+    let body = ErrorResponse {
+        error: kind.to_string(),
+        message,
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&body), status))
+}
+
+/// This function links the service to warp's route handling.
+/// Notes in responses have their timestamps written in `timestamp_format`
+pub fn build_warp_routes<S: NoteStore>(
+    handler: RequestHandler<S>,
+    timestamp_format: TimestampFormat,
+) -> BoxedFilter<(impl Reply,)> {
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_headers(vec!["content-type"])
-        .allow_methods(vec!["GET", "PUT", "POST"]);
+        .allow_headers(vec!["content-type", "if-match"])
+        .expose_headers(vec!["etag"])
+        .allow_methods(vec!["GET", "PUT", "POST", "DELETE"]);
     let handler_filter = warp::any().map(move || handler.clone());
+    let format = warp::any().map(move || timestamp_format);
     let create = warp::post()
         .and(warp::path("notes"))
+        .and(warp::path::end())
         // Only accept bodies smaller than 16kb... (because warp said so)
         // https://github.com/seanmonstar/warp/blob/master/examples/body.rs
         .and(warp::body::content_length_limit(1024 * 16))
@@ -82,9 +509,38 @@ pub fn build_warp_routes<S: NoteStore>(handler: RequestHandler<S>) -> BoxedFilte
     let get_note = warp::get()
         .and(warp::path("note"))
         .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(format)
         .and(handler_filter.clone())
         .and_then(get_note);
 
+    let get_note_history = warp::get()
+        .and(warp::path("note"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("history"))
+        .and(warp::path::end())
+        .and(format)
+        .and(handler_filter.clone())
+        .and_then(get_note_history);
+
+    let diff_note = warp::get()
+        .and(warp::path("note"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("diff"))
+        .and(warp::path::end())
+        .and(warp::query::<DiffQuery>())
+        .and(handler_filter.clone())
+        .and_then(diff_note);
+
+    let revert_note = warp::post()
+        .and(warp::path("note"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("revert"))
+        .and(warp::path::param::<u32>())
+        .and(warp::path::end())
+        .and(handler_filter.clone())
+        .and_then(revert_note);
+
     let archive_note = warp::put()
         .and(warp::path("note"))
         .and(warp::path("archive"))
@@ -92,25 +548,205 @@ pub fn build_warp_routes<S: NoteStore>(handler: RequestHandler<S>) -> BoxedFilte
         .and(handler_filter.clone())
         .and_then(archive_note);
 
+    let unarchive_note = warp::put()
+        .and(warp::path("note"))
+        .and(warp::path("unarchive"))
+        .and(warp::path::param::<Uuid>())
+        .and(handler_filter.clone())
+        .and_then(unarchive_note);
+
+    let purge_note = warp::delete()
+        .and(warp::path("note"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(handler_filter.clone())
+        .and_then(purge_note);
+
     let update_note = warp::put()
         .and(warp::path("note"))
         .and(warp::path::param::<Uuid>())
         .and(warp::body::content_length_limit(1024 * 16))
         .and(warp::body::json())
+        .and(warp::header::optional::<String>("if-match"))
         .and(handler_filter.clone())
         .and_then(update_note);
 
     let get_notes = warp::get()
         .and(warp::path("notes"))
         .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::query::<GetNotesQuery>())
+        .and(format)
         .and(handler_filter.clone())
         .and_then(get_notes);
 
+    let get_archived_notes = warp::get()
+        .and(warp::path("notes"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("archived"))
+        .and(warp::path::end())
+        .and(warp::query::<GetNotesQuery>())
+        .and(format)
+        .and(handler_filter.clone())
+        .and_then(get_archived_notes);
+
+    let search_notes = warp::get()
+        .and(warp::path("notes"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::query::<SearchQuery>())
+        .and(format)
+        .and(handler_filter.clone())
+        .and_then(search_notes);
+
+    let get_tag_facets = warp::get()
+        .and(warp::path("notes"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("tags"))
+        .and(warp::path::end())
+        .and(handler_filter.clone())
+        .and_then(get_tag_facets);
+
+    let rename_tag = warp::post()
+        .and(warp::path("notes"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("tags"))
+        .and(warp::path("rename"))
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::json())
+        .and(format)
+        .and(handler_filter.clone())
+        .and_then(rename_tag);
+
+    let define_tag = warp::post()
+        .and(warp::path("tags"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::json())
+        .and(handler_filter.clone())
+        .and_then(define_tag);
+
+    let get_tag_definitions = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(handler_filter.clone())
+        .and_then(get_tag_definitions);
+
+    let get_tag_tree = warp::get()
+        .and(warp::path("tags"))
+        .and(warp::path::param::<String>())
+        .and(warp::path("tree"))
+        .and(warp::path::end())
+        .and(handler_filter.clone())
+        .and_then(get_tag_tree);
+
+    let create_list = warp::post()
+        .and(warp::path("lists"))
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::json())
+        .and(handler_filter.clone())
+        .and_then(create_list);
+
+    let get_lists = warp::get()
+        .and(warp::path("lists"))
+        .and(warp::path::param::<String>())
+        .and(handler_filter.clone())
+        .and_then(get_lists);
+
+    let get_full_list = warp::get()
+        .and(warp::path("list"))
+        .and(warp::path::param::<Uuid>())
+        .and(format)
+        .and(handler_filter.clone())
+        .and_then(get_full_list);
+
+    let update_list = warp::put()
+        .and(warp::path("list"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::json())
+        .and(handler_filter.clone())
+        .and_then(update_list);
+
+    let delete_list = warp::delete()
+        .and(warp::path("list"))
+        .and(warp::path::param::<Uuid>())
+        .and(handler_filter.clone())
+        .and_then(delete_list);
+
+    let create_person = warp::post()
+        .and(warp::path("people"))
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::json())
+        .and(handler_filter.clone())
+        .and_then(create_person);
+
+    let get_people = warp::get()
+        .and(warp::path("people"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(handler_filter.clone())
+        .and_then(get_people);
+
+    let update_person = warp::put()
+        .and(warp::path("person"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path::end())
+        .and(warp::body::content_length_limit(1024 * 16))
+        .and(warp::body::json())
+        .and(handler_filter.clone())
+        .and_then(update_person);
+
+    let get_recommendations = warp::get()
+        .and(warp::path("person"))
+        .and(warp::path::param::<Uuid>())
+        .and(warp::path("recommendations"))
+        .and(warp::path::end())
+        .and(format)
+        .and(handler_filter.clone())
+        .and_then(get_recommendations);
+
+    let get_followups = warp::get()
+        .and(warp::path("followups"))
+        .and(warp::path::param::<String>())
+        .and(warp::path::end())
+        .and(format)
+        .and(handler_filter.clone())
+        .and_then(get_followups);
+
     let routes = create
         .or(get_note)
         .or(update_note)
         .or(get_notes)
+        .or(get_archived_notes)
+        .or(search_notes)
+        .or(get_tag_facets)
+        .or(rename_tag)
+        .or(define_tag)
+        .or(get_tag_definitions)
+        .or(get_tag_tree)
         .or(archive_note)
+        .or(unarchive_note)
+        .or(purge_note)
+        .or(get_note_history)
+        .or(diff_note)
+        .or(revert_note)
+        .or(create_list)
+        .or(get_lists)
+        .or(get_full_list)
+        .or(update_list)
+        .or(delete_list)
+        .or(create_person)
+        .or(get_people)
+        .or(update_person)
+        .or(get_recommendations)
+        .or(get_followups)
+        .recover(handle_rejection)
         .with(cors)
         .boxed();
     routes
@@ -1,14 +1,53 @@
+use crate::error::{NotesError, Result};
+use crate::history;
 use crate::storage::NoteStore;
+use crate::timestamp;
 use crate::types::{
-    ArchiveNoteRequest, ArchiveNoteResponse, CreateNoteRequest, CreateNoteResponse, GetNoteRequest,
-    GetNoteResponse, GetNotesRequest, GetNotesResponse, Note, Tag, UpdateNoteRequest,
-    UpdateNoteResponse,
+    ArchiveNoteRequest, ArchiveNoteResponse, CreateListRequest, CreateListResponse,
+    CreateNoteRequest, CreateNoteResponse, CreatePersonRequest, CreatePersonResponse,
+    DefineTagRequest, DefineTagResponse, DeleteListRequest, DeleteListResponse, DiffNoteRequest,
+    DiffNoteResponse, Followup, GetFollowupsRequest, GetFollowupsResponse, GetFullListRequest,
+    GetFullListResponse, GetListsRequest, GetListsResponse, GetNoteHistoryRequest,
+    GetNoteHistoryResponse, GetNoteRequest, GetNoteResponse, GetNotesRequest, GetNotesResponse,
+    GetPeopleRequest, GetPeopleResponse, GetRecommendationsRequest, GetRecommendationsResponse,
+    GetTagDefinitionsRequest, GetTagDefinitionsResponse, GetTagFacetsRequest, GetTagFacetsResponse,
+    GetTagTreeRequest, GetTagTreeResponse, List, Note, NoteCursor, NoteRevision, NoteSort,
+    NoteStatus, NotesQuery, Person, PersonSummary, PurgeNoteRequest, PurgeNoteResponse,
+    RenameTagRequest, RenameTagResponse, RevertNoteRequest, RevertNoteResponse, SearchNotesRequest,
+    SearchNotesResponse, Tag, TagDefinition, TagQuery, TagTreeNode, UnarchiveNoteRequest,
+    UnarchiveNoteResponse, UpdateListRequest, UpdateListResponse, UpdateNoteRequest,
+    UpdateNoteResponse, UpdatePersonRequest, UpdatePersonResponse,
 };
-use anyhow::{Context, Result};
-use chrono;
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use uuid::Uuid;
 
+/// Notes per page, when a request doesn't say
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+/// Pick `revision` out of the history of `note_id`
+fn find_revision(history: &[NoteRevision], note_id: Uuid, revision: u32) -> Result<NoteRevision> {
+    history
+        .iter()
+        .find(|past| past.revision == revision)
+        .cloned()
+        .ok_or_else(|| {
+            NotesError::NotFound(format!("Note {} has no revision {}", note_id, revision))
+        })
+}
+
+/// Whether `colour` is written like `#1e90ff`
+fn is_colour(colour: &str) -> bool {
+    colour.len() == 7
+        && colour.starts_with('#')
+        && colour[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Tidy up a name someone typed in
+fn person_name(name: &str) -> String {
+    name.trim().to_string()
+}
+
 #[derive(Clone)]
 pub struct RequestHandler<S> {
     pub storage: S,
@@ -18,11 +57,96 @@ impl<S: NoteStore> RequestHandler<S> {
     pub fn new(storage: S) -> RequestHandler<S> {
         RequestHandler { storage }
     }
+
+    /// Notes can only use the custom tags their owner has defined, and only refer to
+    /// people their owner has added
+    fn check_tags(&self, owner: &str, tags: &HashSet<Tag>) -> Result<()> {
+        self.check_custom_tags(owner, tags)?;
+        let mut referenced: Vec<Uuid> = tags.iter().filter_map(Tag::person_id).collect();
+        if referenced.is_empty() {
+            return Ok(());
+        }
+        referenced.sort();
+        let people: HashSet<Uuid> = self
+            .storage
+            .get_people(owner.to_string())?
+            .into_iter()
+            .map(|person| person.uuid)
+            .collect();
+        match referenced.into_iter().find(|id| !people.contains(id)) {
+            Some(id) => Err(NotesError::InvalidInput(format!(
+                "{} has no person with id {}",
+                owner, id
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn check_custom_tags(&self, owner: &str, tags: &HashSet<Tag>) -> Result<()> {
+        let mut custom: Vec<&String> = tags
+            .iter()
+            .filter_map(|tag| match tag {
+                Tag::Custom(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        if custom.is_empty() {
+            return Ok(());
+        }
+        custom.sort();
+        let defined: HashSet<String> = self
+            .storage
+            .get_tag_definitions(owner.to_string())?
+            .into_iter()
+            .map(|definition| definition.name)
+            .collect();
+        match custom.into_iter().find(|name| !defined.contains(*name)) {
+            Some(name) => Err(NotesError::InvalidInput(format!(
+                "{} hasn't defined the tag {}",
+                owner, name
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// A person needs a name, and none of their names can already mean someone else
+    fn check_person(&self, person: &Person) -> Result<()> {
+        if person.name.is_empty() || person.aliases.iter().any(|alias| alias.is_empty()) {
+            return Err(NotesError::InvalidInput(
+                "A person's name and aliases can't be empty".to_string(),
+            ));
+        }
+        for other in self.storage.get_people(person.owner.clone())? {
+            if other.uuid == person.uuid {
+                continue;
+            }
+            if let Some(name) = person
+                .payloads()
+                .into_iter()
+                .find(|name| other.is_named_by(name))
+            {
+                return Err(NotesError::InvalidInput(format!(
+                    "{} already means {} ({})",
+                    name, other.name, other.uuid
+                )));
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<S: NoteStore> NotesService for RequestHandler<S> {
     fn create_note(&mut self, request: CreateNoteRequest) -> Result<CreateNoteResponse> {
+        if request.title.trim().is_empty() {
+            return Err(NotesError::InvalidInput(
+                "A note must have a title".to_string(),
+            ));
+        }
+        if let Some(tags) = &request.tags {
+            self.check_tags(&request.owner, tags)?;
+        }
         let uuid = Uuid::new_v4();
+        let now = timestamp::now();
         let note = Note {
             uuid: uuid.clone(),
             title: request.title,
@@ -32,51 +156,482 @@ impl<S: NoteStore> NotesService for RequestHandler<S> {
                 Some(tags) => tags,
                 None => HashSet::new(),
             },
-            create_time: format!("{}", chrono::offset::Utc::now().timestamp()),
-            last_update_time: format!("{}", chrono::offset::Utc::now().timestamp()),
+            create_time: now,
+            last_update_time: now,
             delete_time: None,
+            version: 1,
+            status: request.status.unwrap_or_default(),
         };
-        self.storage
-            .create_note(note)
-            .context("Attempting to store note")?;
+        self.storage.create_note(note)?;
         Ok(CreateNoteResponse { note_id: uuid })
     }
     fn get_note(&self, request: GetNoteRequest) -> Result<GetNoteResponse> {
-        let note = self
-            .storage
-            .get_note(request.note_id)
-            .context("Getting Note")?;
+        let note = self.storage.get_note(request.note_id)?;
         Ok(GetNoteResponse { note })
     }
 
     fn get_notes(&self, request: GetNotesRequest) -> Result<GetNotesResponse> {
-        let notes = self
-            .storage
-            .get_notes(request.owner)
-            .context("Getting Notes for owner")?;
-        Ok(GetNotesResponse { notes })
+        let limit = request.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        if limit == 0 || limit > MAX_PAGE_SIZE {
+            return Err(NotesError::InvalidInput(format!(
+                "limit must be between 1 and {}",
+                MAX_PAGE_SIZE
+            )));
+        }
+        let since = request.since.as_deref().map(timestamp::parse).transpose()?;
+        let until = request.until.as_deref().map(timestamp::parse).transpose()?;
+        let after = match &request.cursor {
+            Some(cursor) => Some(NoteCursor::decode(cursor)?),
+            None => None,
+        };
+        if let Some(after) = &after {
+            if after.sort != request.sort {
+                return Err(NotesError::InvalidInput(
+                    "The cursor is from a differently sorted page".to_string(),
+                ));
+            }
+        }
+        let page = self.storage.get_notes(
+            request.owner,
+            NotesQuery {
+                archived: request.archived,
+                status: request.status,
+                tags: request.tags.map(TagQuery::expand),
+                sort: request.sort,
+                since,
+                until,
+                after,
+                limit: Some(limit),
+            },
+        )?;
+        Ok(GetNotesResponse {
+            notes: page.notes,
+            next_cursor: page.next_cursor.map(|cursor| cursor.encode()),
+        })
+    }
+
+    fn search_notes(&self, request: SearchNotesRequest) -> Result<SearchNotesResponse> {
+        if request.query.trim().is_empty() {
+            return Err(NotesError::InvalidInput(
+                "A search needs a query".to_string(),
+            ));
+        }
+        let notes = self.storage.search_notes(request.owner, request.query)?;
+        Ok(SearchNotesResponse { notes })
+    }
+
+    fn get_tag_facets(&self, request: GetTagFacetsRequest) -> Result<GetTagFacetsResponse> {
+        let facets = self.storage.tag_facets(request.owner)?;
+        Ok(GetTagFacetsResponse { facets })
     }
 
     fn archive_note(&mut self, request: ArchiveNoteRequest) -> Result<ArchiveNoteResponse> {
-        self.storage
-            .archive_note(request)
-            .context("getting note to update")?;
+        self.storage.archive_note(request)?;
         Ok(ArchiveNoteResponse {})
     }
 
+    fn unarchive_note(&mut self, request: UnarchiveNoteRequest) -> Result<UnarchiveNoteResponse> {
+        self.storage.unarchive_note(request)?;
+        Ok(UnarchiveNoteResponse {})
+    }
+
+    fn purge_note(&mut self, request: PurgeNoteRequest) -> Result<PurgeNoteResponse> {
+        // Only what is in the trash can be thrown away for good
+        let note = self.storage.get_note(request.note_id)?;
+        if note.delete_time.is_none() {
+            return Err(NotesError::Conflict(format!(
+                "Note {} has to be archived before it can be purged",
+                request.note_id
+            )));
+        }
+        self.storage.purge_note(request.note_id)?;
+        Ok(PurgeNoteResponse {})
+    }
+
     fn update_note(&mut self, request: UpdateNoteRequest) -> Result<UpdateNoteResponse> {
+        if let Some(tags) = &request.tags {
+            let note = self.storage.get_note(request.note_id)?;
+            self.check_tags(&note.owner, tags)?;
+        }
         // Set note in storage
-        self.storage
-            .update_note(request)
-            .context("Attempting to update note")?;
+        self.storage.update_note(request)?;
         Ok(UpdateNoteResponse {})
     }
+
+    fn get_note_history(&self, request: GetNoteHistoryRequest) -> Result<GetNoteHistoryResponse> {
+        let revisions = self.storage.get_note_history(request.note_id)?;
+        Ok(GetNoteHistoryResponse { revisions })
+    }
+
+    fn diff_note(&self, request: DiffNoteRequest) -> Result<DiffNoteResponse> {
+        let history = self.storage.get_note_history(request.note_id)?;
+        let from = find_revision(&history, request.note_id, request.from)?;
+        let to = match request.to {
+            Some(to) => find_revision(&history, request.note_id, to)?,
+            // The current version is the one the next update will record
+            None => {
+                let note = self.storage.get_note(request.note_id)?;
+                let next = history.last().map_or(1, |latest| latest.revision + 1);
+                NoteRevision::of(&note, next)
+            }
+        };
+        Ok(DiffNoteResponse {
+            diff: history::diff(&from, &to),
+        })
+    }
+
+    fn revert_note(&mut self, request: RevertNoteRequest) -> Result<RevertNoteResponse> {
+        let history = self.storage.get_note_history(request.note_id)?;
+        let revision = find_revision(&history, request.note_id, request.revision)?;
+        self.storage.update_note(UpdateNoteRequest {
+            note_id: request.note_id,
+            title: Some(revision.title),
+            description: Some(revision.description),
+            tags: Some(revision.tags),
+            status: None,
+            expected_version: None,
+        })?;
+        Ok(RevertNoteResponse {})
+    }
+
+    fn create_list(&mut self, request: CreateListRequest) -> Result<CreateListResponse> {
+        if request.title.trim().is_empty() {
+            return Err(NotesError::InvalidInput(
+                "A list must have a title".to_string(),
+            ));
+        }
+        let uuid = Uuid::new_v4();
+        let list = List {
+            uuid: uuid.clone(),
+            notes: request.notes.unwrap_or_default(),
+            title: request.title,
+            owner: request.owner,
+            description: request.description,
+        };
+        self.storage.store_list(list)?;
+        Ok(CreateListResponse { list_id: uuid })
+    }
+
+    fn get_lists(&self, request: GetListsRequest) -> Result<GetListsResponse> {
+        let lists = self.storage.get_lists(request.owner)?;
+        Ok(GetListsResponse { lists })
+    }
+
+    fn get_full_list(&self, request: GetFullListRequest) -> Result<GetFullListResponse> {
+        let full_list = self.storage.get_full_list(request.list_id)?;
+        Ok(GetFullListResponse { full_list })
+    }
+
+    fn update_list(&mut self, request: UpdateListRequest) -> Result<UpdateListResponse> {
+        let mut list = self.storage.get_list(request.list_id)?;
+        if let Some(title) = request.title {
+            list.title = title;
+        }
+        if let Some(description) = request.description {
+            list.description = description;
+        }
+        if let Some(notes) = request.notes {
+            list.notes = notes;
+        }
+        self.storage.store_list(list)?;
+        Ok(UpdateListResponse {})
+    }
+
+    fn delete_list(&mut self, request: DeleteListRequest) -> Result<DeleteListResponse> {
+        self.storage.delete_list(request.list_id)?;
+        Ok(DeleteListResponse {})
+    }
+
+    fn define_tag(&mut self, request: DefineTagRequest) -> Result<DefineTagResponse> {
+        let name = request.name.trim().to_string();
+        if name.is_empty() {
+            return Err(NotesError::InvalidInput(
+                "A tag must have a name".to_string(),
+            ));
+        }
+        // Built-in tags can't be shadowed, with or without a payload
+        if Tag::from_parts(&name, None).is_some() || Tag::from_parts(&name, Some("")).is_some() {
+            return Err(NotesError::InvalidInput(format!(
+                "{} is a built-in tag",
+                name
+            )));
+        }
+        if !is_colour(&request.colour) {
+            return Err(NotesError::InvalidInput(format!(
+                "{} is not a colour like #1e90ff",
+                request.colour
+            )));
+        }
+        self.storage.store_tag_definition(TagDefinition {
+            owner: request.owner,
+            name,
+            description: request.description,
+            colour: request.colour.to_lowercase(),
+        })?;
+        Ok(DefineTagResponse {})
+    }
+
+    fn get_tag_definitions(
+        &self,
+        request: GetTagDefinitionsRequest,
+    ) -> Result<GetTagDefinitionsResponse> {
+        let tags = self.storage.get_tag_definitions(request.owner)?;
+        Ok(GetTagDefinitionsResponse { tags })
+    }
+
+    fn rename_tag(&mut self, request: RenameTagRequest) -> Result<RenameTagResponse> {
+        if request.from == request.to {
+            return Err(NotesError::InvalidInput(
+                "A tag can't be renamed to itself".to_string(),
+            ));
+        }
+        let to: HashSet<Tag> = vec![request.to.clone()].into_iter().collect();
+        self.check_tags(&request.owner, &to)?;
+        let mut notes =
+            self.storage
+                .rename_tag(request.owner, request.from, request.to, request.dry_run)?;
+        notes.sort_by(|note, other| {
+            note.title
+                .cmp(&other.title)
+                .then_with(|| note.uuid.cmp(&other.uuid))
+        });
+        Ok(RenameTagResponse { notes })
+    }
+
+    fn create_person(&mut self, request: CreatePersonRequest) -> Result<CreatePersonResponse> {
+        let person = Person {
+            uuid: Uuid::new_v4(),
+            owner: request.owner,
+            name: person_name(&request.name),
+            aliases: request
+                .aliases
+                .iter()
+                .map(|alias| person_name(alias))
+                .collect(),
+            description: request.description,
+        };
+        self.check_person(&person)?;
+        let person_id = person.uuid;
+        self.storage.store_person(person)?;
+        Ok(CreatePersonResponse { person_id })
+    }
+
+    fn update_person(&mut self, request: UpdatePersonRequest) -> Result<UpdatePersonResponse> {
+        let mut person = self.storage.get_person(request.person_id)?;
+        if let Some(aliases) = request.aliases {
+            person.aliases = aliases.iter().map(|alias| person_name(alias)).collect();
+        }
+        if let Some(name) = request.name {
+            let name = person_name(&name);
+            // Tags that still use the old name keep meaning this person
+            if name != person.name && !person.aliases.contains(&person.name) {
+                person.aliases.push(person.name.clone());
+            }
+            person.name = name;
+        }
+        person.aliases.retain(|alias| alias != &person.name);
+        if let Some(description) = request.description {
+            person.description = description;
+        }
+        self.check_person(&person)?;
+        self.storage.store_person(person)?;
+        Ok(UpdatePersonResponse {})
+    }
+
+    fn get_people(&self, request: GetPeopleRequest) -> Result<GetPeopleResponse> {
+        let people = self.storage.get_people(request.owner.clone())?;
+        let mut known: Vec<PersonSummary> = people
+            .iter()
+            .map(|person| PersonSummary {
+                person_id: Some(person.uuid),
+                name: person.name.clone(),
+                ..PersonSummary::default()
+            })
+            .collect();
+        let mut unknown: BTreeMap<String, PersonSummary> = BTreeMap::new();
+        let mentioned = TagQuery::Or(
+            Box::new(TagQuery::AnyPayload("RecommendedBy".to_string())),
+            Box::new(TagQuery::AnyPayload("RemindsMeOf".to_string())),
+        );
+        let notes = self
+            .storage
+            .get_notes(
+                request.owner,
+                NotesQuery {
+                    tags: Some(mentioned),
+                    ..NotesQuery::default()
+                },
+            )?
+            .notes;
+        for note in &notes {
+            // Whether each tag is a recommendation, and who it names: the index of a
+            // known person, or else the name itself. A note counts once per person,
+            // however many of their names it is tagged with
+            let mentions: HashSet<(bool, Result<usize, &str>)> = note
+                .tags
+                .iter()
+                .filter_map(|tag| {
+                    let (recommendation, payload) = match tag {
+                        Tag::RecommendedBy(payload) => (true, payload.as_str()),
+                        Tag::RemindsMeOf(payload) => (false, payload.as_str()),
+                        _ => return None,
+                    };
+                    let who = people
+                        .iter()
+                        .position(|person| person.is_named_by(payload))
+                        .ok_or(payload);
+                    Some((recommendation, who))
+                })
+                .collect();
+            for (recommendation, who) in mentions {
+                let summary = match who {
+                    Ok(index) => &mut known[index],
+                    Err(name) => unknown
+                        .entry(name.to_string())
+                        .or_insert_with(|| PersonSummary {
+                            name: name.to_string(),
+                            ..PersonSummary::default()
+                        }),
+                };
+                if recommendation {
+                    summary.recommendations += 1;
+                } else {
+                    summary.reminders += 1;
+                }
+            }
+        }
+        let mut people: Vec<PersonSummary> =
+            known.into_iter().chain(unknown.into_values()).collect();
+        people.sort_by(|person, other| {
+            (other.recommendations + other.reminders)
+                .cmp(&(person.recommendations + person.reminders))
+                .then_with(|| person.name.cmp(&other.name))
+        });
+        Ok(GetPeopleResponse { people })
+    }
+
+    fn get_recommendations(
+        &self,
+        request: GetRecommendationsRequest,
+    ) -> Result<GetRecommendationsResponse> {
+        let person = self.storage.get_person(request.person_id)?;
+        let tags = person
+            .payloads()
+            .into_iter()
+            .map(|payload| TagQuery::Tag(Tag::RecommendedBy(payload)))
+            .reduce(|query, name| TagQuery::Or(Box::new(query), Box::new(name)));
+        let notes = self
+            .storage
+            .get_notes(
+                person.owner.clone(),
+                NotesQuery {
+                    tags,
+                    sort: NoteSort::Title,
+                    ..NotesQuery::default()
+                },
+            )?
+            .notes;
+        Ok(GetRecommendationsResponse { person, notes })
+    }
+
+    fn get_followups(&self, request: GetFollowupsRequest) -> Result<GetFollowupsResponse> {
+        let people = self.storage.get_people(request.owner.clone())?;
+        // Who a tag is about, and what they're called. Payloads naming the same `Person`
+        // are about the same someone, anything else only matches when spelled the same
+        let who = |payload: &str| match people.iter().find(|person| person.is_named_by(payload)) {
+            Some(person) => (person.reference(), person.name.clone()),
+            None => (payload.to_string(), payload.to_string()),
+        };
+        let notes = self
+            .storage
+            .get_notes(
+                request.owner,
+                NotesQuery {
+                    status: Some(NoteStatus::Done),
+                    tags: Some(TagQuery::AnyPayload("RecommendedBy".to_string())),
+                    sort: NoteSort::LastUpdateTime,
+                    ..NotesQuery::default()
+                },
+            )?
+            .notes;
+        let mut followups: Vec<Followup> = notes
+            .into_iter()
+            .filter_map(|note| {
+                let talked_to: HashSet<String> = note
+                    .tags
+                    .iter()
+                    .filter_map(|tag| match tag {
+                        Tag::TalkedTo(payload) => Some(who(payload).0),
+                        _ => None,
+                    })
+                    .collect();
+                let people: BTreeSet<String> = note
+                    .tags
+                    .iter()
+                    .filter_map(|tag| match tag {
+                        Tag::RecommendedBy(payload) => Some(who(payload)),
+                        _ => None,
+                    })
+                    .filter(|(person, _)| !talked_to.contains(person))
+                    .map(|(_, name)| name)
+                    .collect();
+                if people.is_empty() {
+                    return None;
+                }
+                Some(Followup {
+                    note,
+                    people: people.into_iter().collect(),
+                })
+            })
+            .collect();
+        followups.reverse();
+        Ok(GetFollowupsResponse { followups })
+    }
+
+    fn get_tag_tree(&self, request: GetTagTreeRequest) -> Result<GetTagTreeResponse> {
+        let custom = self
+            .storage
+            .get_tag_definitions(request.owner)?
+            .into_iter()
+            .map(|definition| Tag::Custom(definition.name))
+            .collect();
+        Ok(GetTagTreeResponse {
+            tags: TagTreeNode::tree(custom),
+        })
+    }
 }
 
 pub trait NotesService: Send + Sync + Clone + 'static {
     fn create_note(&mut self, request: CreateNoteRequest) -> Result<CreateNoteResponse>;
     fn get_note(&self, request: GetNoteRequest) -> Result<GetNoteResponse>;
     fn get_notes(&self, request: GetNotesRequest) -> Result<GetNotesResponse>;
+    fn search_notes(&self, request: SearchNotesRequest) -> Result<SearchNotesResponse>;
+    fn get_tag_facets(&self, request: GetTagFacetsRequest) -> Result<GetTagFacetsResponse>;
     fn update_note(&mut self, request: UpdateNoteRequest) -> Result<UpdateNoteResponse>;
     fn archive_note(&mut self, request: ArchiveNoteRequest) -> Result<ArchiveNoteResponse>;
+    fn unarchive_note(&mut self, request: UnarchiveNoteRequest) -> Result<UnarchiveNoteResponse>;
+    fn purge_note(&mut self, request: PurgeNoteRequest) -> Result<PurgeNoteResponse>;
+    fn get_note_history(&self, request: GetNoteHistoryRequest) -> Result<GetNoteHistoryResponse>;
+    fn diff_note(&self, request: DiffNoteRequest) -> Result<DiffNoteResponse>;
+    fn revert_note(&mut self, request: RevertNoteRequest) -> Result<RevertNoteResponse>;
+    fn create_list(&mut self, request: CreateListRequest) -> Result<CreateListResponse>;
+    fn get_lists(&self, request: GetListsRequest) -> Result<GetListsResponse>;
+    fn get_full_list(&self, request: GetFullListRequest) -> Result<GetFullListResponse>;
+    fn update_list(&mut self, request: UpdateListRequest) -> Result<UpdateListResponse>;
+    fn delete_list(&mut self, request: DeleteListRequest) -> Result<DeleteListResponse>;
+    fn define_tag(&mut self, request: DefineTagRequest) -> Result<DefineTagResponse>;
+    fn get_tag_definitions(
+        &self,
+        request: GetTagDefinitionsRequest,
+    ) -> Result<GetTagDefinitionsResponse>;
+    fn rename_tag(&mut self, request: RenameTagRequest) -> Result<RenameTagResponse>;
+    fn create_person(&mut self, request: CreatePersonRequest) -> Result<CreatePersonResponse>;
+    fn update_person(&mut self, request: UpdatePersonRequest) -> Result<UpdatePersonResponse>;
+    fn get_people(&self, request: GetPeopleRequest) -> Result<GetPeopleResponse>;
+    fn get_recommendations(
+        &self,
+        request: GetRecommendationsRequest,
+    ) -> Result<GetRecommendationsResponse>;
+    fn get_followups(&self, request: GetFollowupsRequest) -> Result<GetFollowupsResponse>;
+    fn get_tag_tree(&self, request: GetTagTreeRequest) -> Result<GetTagTreeResponse>;
 }
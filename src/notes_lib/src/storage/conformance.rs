@@ -0,0 +1,1238 @@
+//! A backend agnostic test suite for `NoteStore` implementations.
+//!
+//! Every backend should behave exactly like every other, so new backends (and
+//! changes to old ones) should get a test for every check with `conformance_tests!`:
+//!
+//! ```ignore
+//! use notes_lib::storage::MemoryNoteStore;
+//!
+//! fn with_memory_store(check: fn(MemoryNoteStore)) {
+//!     check(MemoryNoteStore::new());
+//! }
+//!
+//! notes_lib::conformance_tests!(with_memory_store);
+//! ```
+//!
+//! Only built for tests, or with the `conformance` feature.
+//!
+//! Checks panic on failure, like any other test assertion. Each check creates its
+//! own owner, so stores backed by a shared database don't need to be emptied between them.
+use crate::error::NotesError;
+use crate::storage::NoteStore;
+use crate::timestamp;
+use crate::types::{
+    ArchiveNoteRequest, ArchivedNotes, List, Note, NoteRevision, NoteSort, NoteStatus, NotesQuery,
+    Person, Tag, TagDefinition, TagFacet, TagValueCount, UnarchiveNoteRequest, UpdateNoteRequest,
+};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// Defines a `#[test]` for every check, each calling `$run` with the check.
+/// `$run` builds a fresh store, hands it to the check and cleans up after it
+#[macro_export]
+macro_rules! conformance_tests {
+    ($run:path) => {
+        $crate::conformance_tests!(
+            @checks $run;
+            create_and_get_note,
+            create_note_without_tags,
+            timestamps_round_trip,
+            tag_payloads_round_trip,
+            get_notes_only_returns_owners_notes,
+            get_notes_for_unknown_owner,
+            update_note_persists,
+            empty_update_keeps_fields,
+            update_note_records_history,
+            rename_tag,
+            rename_tag_dry_run,
+            changes_bump_version,
+            update_note_checks_version,
+            archive_note_persists,
+            missing_notes_are_not_found,
+            get_notes_hides_archived_notes,
+            unarchive_note_restores_note,
+            purge_note_deletes_note,
+            purge_note_leaves_its_lists,
+            purge_note_forgets_history,
+            purge_archived_before,
+            sort_notes,
+            sort_ties_are_broken_by_uuid,
+            paginate_notes,
+            filter_notes_by_create_time,
+            filter_notes_by_status,
+            filter_notes_by_tag,
+            filter_notes_by_tag_query,
+            filter_notes_by_any_payload,
+            tag_facets,
+            filter_notes_by_custom_tag,
+            store_tag_definitions,
+            store_and_get_people,
+            search_notes,
+            search_sees_updates,
+            search_only_returns_owners_notes,
+            store_and_get_lists,
+            empty_list,
+            list_can_repeat_notes,
+            list_keeps_archived_notes,
+            list_with_missing_note,
+            delete_list,
+        );
+    };
+    (@checks $run:path; $($check:ident,)*) => {
+        $(
+            #[test]
+            fn $check() {
+                $run($crate::storage::conformance::$check);
+            }
+        )*
+    };
+}
+
+/// Every check gets a fresh owner, so they can share a database
+fn new_owner() -> String {
+    format!("test-{}", Uuid::new_v4())
+}
+
+/// The time `seconds` after the unix epoch
+fn at(seconds: i64) -> DateTime<Utc> {
+    timestamp::from_epoch_seconds(seconds)
+}
+
+fn new_note(owner: &str, title: &str) -> Note {
+    Note {
+        uuid: Uuid::new_v4(),
+        title: title.to_string(),
+        owner: owner.to_string(),
+        description: format!("All about {}", title),
+        tags: vec![Tag::Book, Tag::RecommendedBy("Alice".to_string())]
+            .into_iter()
+            .collect(),
+        create_time: at(1609459200),
+        last_update_time: at(1609459200),
+        delete_time: None,
+        version: 1,
+        status: NoteStatus::Wishlist,
+    }
+}
+
+fn new_list(owner: &str, notes: Vec<Uuid>) -> List {
+    List {
+        uuid: Uuid::new_v4(),
+        notes,
+        title: "To Read".to_string(),
+        owner: owner.to_string(),
+        description: "Books to read next".to_string(),
+    }
+}
+
+pub fn create_and_get_note<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert_eq!(stored.title, note.title);
+    assert_eq!(stored.owner, note.owner);
+    assert_eq!(stored.description, note.description);
+    assert_eq!(stored.tags, note.tags);
+    assert_eq!(stored.delete_time, None);
+}
+
+pub fn timestamps_round_trip<S: NoteStore>(mut store: S) {
+    let note = Note {
+        create_time: at(1609459200) + Duration::microseconds(123_456),
+        last_update_time: at(1609459300) + Duration::microseconds(1),
+        delete_time: Some(at(1609459400) + Duration::microseconds(999_999)),
+        ..new_note(&new_owner(), "Deep Work")
+    };
+    store.create_note(note.clone()).unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert_eq!(stored.create_time, note.create_time);
+    assert_eq!(stored.last_update_time, note.last_update_time);
+    assert_eq!(stored.delete_time, note.delete_time);
+}
+
+pub fn get_notes_only_returns_owners_notes<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let first = new_note(&owner, "Deep Work");
+    let second = new_note(&owner, "Dune");
+    let someone_elses = new_note(&new_owner(), "Hyperion");
+    for note in vec![first.clone(), second.clone(), someone_elses] {
+        store.create_note(note).unwrap();
+    }
+
+    let page = store.get_notes(owner, NotesQuery::default()).unwrap();
+    let mut ids: Vec<Uuid> = page.notes.iter().map(|note| note.uuid).collect();
+    ids.sort();
+    let mut expected = vec![first.uuid, second.uuid];
+    expected.sort();
+    assert_eq!(ids, expected);
+    assert_eq!(page.next_cursor, None);
+}
+
+fn titles(notes: &[Note]) -> Vec<&str> {
+    notes.iter().map(|note| note.title.as_str()).collect()
+}
+
+/// Create notes of a fresh owner with the given titles, create times and update times
+fn create_timed_notes<S: NoteStore>(store: &mut S, notes: Vec<(&str, i64, i64)>) -> String {
+    let owner = new_owner();
+    for (title, create_time, last_update_time) in notes {
+        store
+            .create_note(Note {
+                create_time: at(create_time),
+                last_update_time: at(last_update_time),
+                ..new_note(&owner, title)
+            })
+            .unwrap();
+    }
+    owner
+}
+
+pub fn sort_notes<S: NoteStore>(mut store: S) {
+    let owner = create_timed_notes(
+        &mut store,
+        vec![
+            ("Dune", 1609459300, 1609459300),
+            ("Arrival", 1609459200, 1609459500),
+            ("Hyperion", 1609459400, 1609459400),
+        ],
+    );
+    let sorted = |sort| {
+        let query = NotesQuery {
+            sort,
+            ..NotesQuery::default()
+        };
+        store.get_notes(owner.clone(), query).unwrap().notes
+    };
+    assert_eq!(
+        titles(&sorted(NoteSort::CreateTime)),
+        vec!["Arrival", "Dune", "Hyperion"]
+    );
+    assert_eq!(
+        titles(&sorted(NoteSort::LastUpdateTime)),
+        vec!["Dune", "Hyperion", "Arrival"]
+    );
+    assert_eq!(
+        titles(&sorted(NoteSort::Title)),
+        vec!["Arrival", "Dune", "Hyperion"]
+    );
+}
+
+pub fn sort_ties_are_broken_by_uuid<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let mut ids = Vec::new();
+    for _ in 0..4 {
+        let note = new_note(&owner, "Dune");
+        ids.push(note.uuid);
+        store.create_note(note).unwrap();
+    }
+    ids.sort();
+    for sort in vec![
+        NoteSort::CreateTime,
+        NoteSort::LastUpdateTime,
+        NoteSort::Title,
+    ] {
+        let query = NotesQuery {
+            sort,
+            ..NotesQuery::default()
+        };
+        let notes = store.get_notes(owner.clone(), query).unwrap().notes;
+        let found: Vec<Uuid> = notes.iter().map(|note| note.uuid).collect();
+        assert_eq!(found, ids);
+    }
+}
+
+pub fn paginate_notes<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    for title in vec!["E", "B", "D", "A", "C"] {
+        store.create_note(new_note(&owner, title)).unwrap();
+    }
+
+    let mut pages = Vec::new();
+    let mut after = None;
+    loop {
+        let query = NotesQuery {
+            sort: NoteSort::Title,
+            after,
+            limit: Some(2),
+            ..NotesQuery::default()
+        };
+        let page = store.get_notes(owner.clone(), query).unwrap();
+        pages.push(titles(&page.notes).join(""));
+        after = page.next_cursor;
+        if after.is_none() {
+            break;
+        }
+    }
+    assert_eq!(pages, vec!["AB", "CD", "E"]);
+
+    // A page that ends exactly on the last note doesn't point at an empty page
+    let query = NotesQuery {
+        limit: Some(5),
+        ..NotesQuery::default()
+    };
+    let page = store.get_notes(owner, query).unwrap();
+    assert_eq!(page.notes.len(), 5);
+    assert_eq!(page.next_cursor, None);
+}
+
+pub fn filter_notes_by_create_time<S: NoteStore>(mut store: S) {
+    let owner = create_timed_notes(
+        &mut store,
+        vec![
+            ("Arrival", 1609459200, 1609459200),
+            ("Dune", 1609459300, 1609459300),
+            ("Hyperion", 1609459400, 1609459400),
+        ],
+    );
+    let between = |since: Option<i64>, until: Option<i64>| {
+        let query = NotesQuery {
+            since: since.map(at),
+            until: until.map(at),
+            ..NotesQuery::default()
+        };
+        let notes = store.get_notes(owner.clone(), query).unwrap().notes;
+        titles(&notes).join(", ")
+    };
+    assert_eq!(between(Some(1609459300), None), "Dune, Hyperion");
+    assert_eq!(between(None, Some(1609459300)), "Arrival");
+    assert_eq!(between(Some(1609459200), Some(1609459400)), "Arrival, Dune");
+    assert_eq!(between(Some(1609459500), None), "");
+}
+
+pub fn filter_notes_by_status<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let mut uuids = Vec::new();
+    for (title, status) in [
+        ("Arrival", NoteStatus::Done),
+        ("Dune", NoteStatus::InProgress),
+        ("Hyperion", NoteStatus::Wishlist),
+        ("Ulysses", NoteStatus::Abandoned),
+    ] {
+        let note = Note {
+            status,
+            ..new_note(&owner, title)
+        };
+        uuids.push(note.uuid);
+        store.create_note(note).unwrap();
+    }
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: uuids[1],
+            status: Some(NoteStatus::Done),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+
+    let with_status = |status: NoteStatus| {
+        let query = NotesQuery {
+            status: Some(status),
+            sort: NoteSort::Title,
+            ..NotesQuery::default()
+        };
+        let notes = store.get_notes(owner.clone(), query).unwrap().notes;
+        titles(&notes).join(", ")
+    };
+    assert_eq!(with_status(NoteStatus::Done), "Arrival, Dune");
+    assert_eq!(with_status(NoteStatus::InProgress), "");
+    assert_eq!(with_status(NoteStatus::Wishlist), "Hyperion");
+    assert_eq!(with_status(NoteStatus::Abandoned), "Ulysses");
+    assert_eq!(store.get_note(uuids[1]).unwrap().status, NoteStatus::Done);
+}
+
+/// Create notes of a fresh owner with the given titles and tags
+fn create_tagged_notes<S: NoteStore>(store: &mut S, notes: Vec<(&str, Vec<Tag>)>) -> String {
+    let owner = new_owner();
+    for (title, tags) in notes {
+        store
+            .create_note(Note {
+                tags: tags.into_iter().collect(),
+                ..new_note(&owner, title)
+            })
+            .unwrap();
+    }
+    owner
+}
+
+/// Titles of the notes of `owner` that match the tag query `tags`, sorted
+fn matching_titles<S: NoteStore>(store: &S, owner: &str, tags: &str) -> Vec<String> {
+    let query = NotesQuery {
+        tags: Some(tags.parse().unwrap()),
+        sort: NoteSort::Title,
+        ..NotesQuery::default()
+    };
+    let notes = store.get_notes(owner.to_string(), query).unwrap().notes;
+    notes.into_iter().map(|note| note.title).collect()
+}
+
+pub fn filter_notes_by_tag<S: NoteStore>(mut store: S) {
+    let owner = create_tagged_notes(
+        &mut store,
+        vec![
+            ("Deep Work", vec![Tag::Book, Tag::Productivity]),
+            ("Dune", vec![Tag::Book]),
+            ("Arrival", vec![Tag::Movie]),
+            ("Untagged", vec![]),
+        ],
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Book"),
+        vec!["Deep Work", "Dune"]
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Productivity"),
+        vec!["Deep Work"]
+    );
+    assert!(matching_titles(&store, &owner, "Podcast").is_empty());
+    assert_eq!(
+        matching_titles(&store, &owner, "NOT Book"),
+        vec!["Arrival", "Untagged"]
+    );
+}
+
+pub fn filter_notes_by_tag_query<S: NoteStore>(mut store: S) {
+    let alice = Tag::RecommendedBy("Alice".to_string());
+    let bob = Tag::RecommendedBy("Bob".to_string());
+    let owner = create_tagged_notes(
+        &mut store,
+        vec![
+            (
+                "Deep Work",
+                vec![Tag::Book, Tag::Productivity, alice.clone()],
+            ),
+            ("So Good", vec![Tag::Book, Tag::Career, bob.clone()]),
+            (
+                "Getting Things Done",
+                vec![Tag::Book, Tag::Productivity, bob],
+            ),
+            ("Dune", vec![Tag::Book, alice.clone()]),
+            ("Arrival", vec![Tag::Movie, alice]),
+        ],
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Book AND (Productivity OR Career)"),
+        vec!["Deep Work", "Getting Things Done", "So Good"]
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Book AND RecommendedBy(\"Alice\")"),
+        vec!["Deep Work", "Dune"]
+    );
+    assert_eq!(
+        matching_titles(
+            &store,
+            &owner,
+            "Movie or not RecommendedBy(\"Bob\") and Productivity"
+        ),
+        vec!["Arrival", "Deep Work"]
+    );
+}
+
+pub fn filter_notes_by_any_payload<S: NoteStore>(mut store: S) {
+    let owner = create_tagged_notes(
+        &mut store,
+        vec![
+            (
+                "Deep Work",
+                vec![Tag::Book, Tag::Origin("Twitter".to_string())],
+            ),
+            (
+                "Dune",
+                vec![Tag::Book, Tag::RemindsMeOf("Hyperion".to_string())],
+            ),
+            (
+                "Arrival",
+                vec![Tag::Movie, Tag::Origin("Podcast".to_string())],
+            ),
+        ],
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Origin(*)"),
+        vec!["Arrival", "Deep Work"]
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Book AND NOT Origin(*)"),
+        vec!["Dune"]
+    );
+    assert!(matching_titles(&store, &owner, "RecommendedBy(*)").is_empty());
+}
+
+pub fn tag_facets<S: NoteStore>(mut store: S) {
+    let alice = Tag::RecommendedBy("Alice".to_string());
+    let bob = Tag::RecommendedBy("Bob".to_string());
+    let owner = create_tagged_notes(
+        &mut store,
+        vec![
+            ("Deep Work", vec![Tag::Book, alice.clone(), bob.clone()]),
+            ("Dune", vec![Tag::Book, alice]),
+            ("Arrival", vec![Tag::Movie]),
+            ("Untagged", vec![]),
+            ("Archived", vec![Tag::Podcast, bob]),
+        ],
+    );
+    let notes = store
+        .get_notes(owner.clone(), NotesQuery::default())
+        .unwrap()
+        .notes;
+    let archived = notes.iter().find(|note| note.title == "Archived").unwrap();
+    store
+        .archive_note(ArchiveNoteRequest {
+            note_id: archived.uuid,
+        })
+        .unwrap();
+
+    let facet = |kind: &str, count: u64, values: Vec<(&str, u64)>| TagFacet {
+        kind: kind.to_string(),
+        count,
+        values: values
+            .into_iter()
+            .map(|(value, count)| TagValueCount {
+                value: value.to_string(),
+                count,
+            })
+            .collect(),
+    };
+    assert_eq!(
+        store.tag_facets(owner).unwrap(),
+        vec![
+            facet("Book", 2, vec![]),
+            facet("RecommendedBy", 2, vec![("Alice", 2), ("Bob", 1)]),
+            facet("Movie", 1, vec![]),
+        ]
+    );
+    assert!(store.tag_facets(new_owner()).unwrap().is_empty());
+}
+
+pub fn filter_notes_by_custom_tag<S: NoteStore>(mut store: S) {
+    let museum = Tag::Custom("Museum".to_string());
+    let owner = create_tagged_notes(
+        &mut store,
+        vec![
+            (
+                "Louvre",
+                vec![museum.clone(), Tag::RemindsMeOf("Paris".to_string())],
+            ),
+            ("Tate", vec![museum.clone()]),
+            ("Dune", vec![Tag::Book]),
+        ],
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Custom(\"Museum\")"),
+        vec!["Louvre", "Tate"]
+    );
+    assert_eq!(
+        matching_titles(&store, &owner, "Custom(*) AND NOT RemindsMeOf(*)"),
+        vec!["Tate"]
+    );
+    let notes = store.get_notes(owner, NotesQuery::default()).unwrap().notes;
+    let tate = notes.iter().find(|note| note.title == "Tate").unwrap();
+    assert_eq!(tate.tags, vec![museum].into_iter().collect());
+}
+
+fn tag_definition(owner: &str, name: &str, colour: &str) -> TagDefinition {
+    TagDefinition {
+        owner: owner.to_string(),
+        name: name.to_string(),
+        description: format!("Notes about {}", name),
+        colour: colour.to_string(),
+    }
+}
+
+pub fn store_tag_definitions<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    assert!(store.get_tag_definitions(owner.clone()).unwrap().is_empty());
+    for name in &["Museum", "Gallery", "Concert"] {
+        store
+            .store_tag_definition(tag_definition(&owner, name, "#1e90ff"))
+            .unwrap();
+    }
+    // Defining a tag again replaces it
+    store
+        .store_tag_definition(tag_definition(&owner, "Gallery", "#ff0000"))
+        .unwrap();
+    store
+        .store_tag_definition(tag_definition(&new_owner(), "Museum", "#000000"))
+        .unwrap();
+
+    assert_eq!(
+        store.get_tag_definitions(owner.clone()).unwrap(),
+        vec![
+            tag_definition(&owner, "Concert", "#1e90ff"),
+            tag_definition(&owner, "Gallery", "#ff0000"),
+            tag_definition(&owner, "Museum", "#1e90ff"),
+        ]
+    );
+}
+
+fn new_person(owner: &str, name: &str, aliases: Vec<&str>) -> Person {
+    Person {
+        uuid: Uuid::new_v4(),
+        owner: owner.to_string(),
+        name: name.to_string(),
+        aliases: aliases.into_iter().map(String::from).collect(),
+        description: format!("Knows about {}", name),
+    }
+}
+
+pub fn store_and_get_people<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let alice = new_person(&owner, "Alice", vec!["alice", "Alice S."]);
+    let bob = new_person(&owner, "Bob", vec![]);
+    store.store_person(bob.clone()).unwrap();
+    store.store_person(alice.clone()).unwrap();
+    store
+        .store_person(new_person(&new_owner(), "Carol", vec![]))
+        .unwrap();
+    assert_eq!(store.get_person(alice.uuid).unwrap(), alice);
+    assert_eq!(
+        store.get_people(owner.clone()).unwrap(),
+        vec![alice.clone(), bob.clone()]
+    );
+
+    // Storing a person again replaces them
+    let robert = Person {
+        name: "Robert".to_string(),
+        aliases: vec!["Bob".to_string()],
+        ..bob
+    };
+    store.store_person(robert.clone()).unwrap();
+    assert_eq!(store.get_person(robert.uuid).unwrap(), robert);
+    assert_eq!(store.get_people(owner).unwrap(), vec![alice, robert]);
+
+    assert!(matches!(
+        store.get_person(Uuid::new_v4()),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(store.get_people(new_owner()).unwrap().is_empty());
+}
+
+pub fn update_note_persists<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+
+    let tags: HashSet<Tag> = vec![Tag::Book, Tag::Productivity].into_iter().collect();
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            title: Some("Deep Work: Rules for Focused Success".to_string()),
+            description: None,
+            tags: Some(tags.clone()),
+            status: None,
+            expected_version: None,
+        })
+        .unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert_eq!(stored.title, "Deep Work: Rules for Focused Success");
+    assert_eq!(stored.description, note.description);
+    assert_eq!(stored.tags, tags);
+    assert_ne!(stored.last_update_time, note.last_update_time);
+}
+
+pub fn update_note_records_history<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+    assert!(store.get_note_history(note.uuid).unwrap().is_empty());
+
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            title: Some("Deep Work: Rules for Focused Success".to_string()),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+    let renamed = store.get_note(note.uuid).unwrap();
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            description: Some("Focus is a skill".to_string()),
+            tags: Some(vec![Tag::Productivity].into_iter().collect()),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+
+    assert_eq!(
+        store.get_note_history(note.uuid).unwrap(),
+        vec![NoteRevision::of(&note, 1), NoteRevision::of(&renamed, 2)]
+    );
+}
+
+/// Notes of a fresh owner recommended by Alice under a few spellings
+fn create_misspelled_notes<S: NoteStore>(store: &mut S) -> String {
+    let recommended_by = |name: &str| Tag::RecommendedBy(name.to_string());
+    create_tagged_notes(
+        store,
+        vec![
+            ("Deep Work", vec![Tag::Book, recommended_by("alice")]),
+            (
+                "Dune",
+                vec![recommended_by("alice"), recommended_by("Alice")],
+            ),
+            ("Arrival", vec![recommended_by("Alice")]),
+            ("So Good", vec![recommended_by("Bob")]),
+        ],
+    )
+}
+
+fn notes_by_title<S: NoteStore>(store: &S, owner: &str) -> Vec<Note> {
+    let query = NotesQuery {
+        archived: ArchivedNotes::Include,
+        sort: NoteSort::Title,
+        ..NotesQuery::default()
+    };
+    store.get_notes(owner.to_string(), query).unwrap().notes
+}
+
+pub fn rename_tag<S: NoteStore>(mut store: S) {
+    let alice = Tag::RecommendedBy("Alice".to_string());
+    let owner = create_misspelled_notes(&mut store);
+    // Someone else's notes are left alone
+    let other = create_misspelled_notes(&mut store);
+    let before = notes_by_title(&store, &owner);
+    let dune = before.iter().find(|note| note.title == "Dune").unwrap();
+    store
+        .archive_note(ArchiveNoteRequest { note_id: dune.uuid })
+        .unwrap();
+
+    let mut renamed = store
+        .rename_tag(
+            owner.clone(),
+            Tag::RecommendedBy("alice".to_string()),
+            alice.clone(),
+            false,
+        )
+        .unwrap();
+    renamed.sort_by(|note, other| note.title.cmp(&other.title));
+    assert_eq!(titles(&renamed), vec!["Deep Work", "Dune"]);
+
+    let after = notes_by_title(&store, &owner);
+    let tags = |title: &str| {
+        let note = after.iter().find(|note| note.title == title).unwrap();
+        note.tags.clone()
+    };
+    assert_eq!(
+        tags("Deep Work"),
+        vec![Tag::Book, alice.clone()].into_iter().collect()
+    );
+    // Notes that had both spellings end up with one
+    assert_eq!(tags("Dune"), vec![alice.clone()].into_iter().collect());
+    assert_eq!(tags("Arrival"), vec![alice].into_iter().collect());
+    let deep_work = after.iter().find(|note| note.title == "Deep Work").unwrap();
+    assert_eq!(deep_work.version, 2);
+    let history = store.get_note_history(deep_work.uuid).unwrap();
+    assert_eq!(history.len(), 1);
+    assert!(history[0]
+        .tags
+        .contains(&Tag::RecommendedBy("alice".to_string())));
+    let untouched = after.iter().find(|note| note.title == "Arrival").unwrap();
+    assert_eq!(untouched.version, 1);
+
+    assert_eq!(
+        matching_titles(&store, &other, "RecommendedBy(\"alice\")"),
+        vec!["Deep Work", "Dune"]
+    );
+}
+
+pub fn rename_tag_dry_run<S: NoteStore>(mut store: S) {
+    let owner = create_misspelled_notes(&mut store);
+    let before = notes_by_title(&store, &owner);
+    let mut renamed = store
+        .rename_tag(
+            owner.clone(),
+            Tag::RecommendedBy("alice".to_string()),
+            Tag::RecommendedBy("Alice".to_string()),
+            true,
+        )
+        .unwrap();
+    renamed.sort_by(|note, other| note.title.cmp(&other.title));
+    assert_eq!(titles(&renamed), vec!["Deep Work", "Dune"]);
+    let after = notes_by_title(&store, &owner);
+    for (before, after) in before.iter().zip(&after) {
+        assert_eq!(before.tags, after.tags);
+        assert_eq!(before.version, after.version);
+    }
+    assert!(store
+        .rename_tag(owner, Tag::Podcast, Tag::Movie, false)
+        .unwrap()
+        .is_empty());
+}
+
+pub fn changes_bump_version<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+    assert_eq!(store.get_note(note.uuid).unwrap().version, 1);
+
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            title: Some("Dune".to_string()),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+    assert_eq!(store.get_note(note.uuid).unwrap().version, 2);
+    store
+        .archive_note(ArchiveNoteRequest { note_id: note.uuid })
+        .unwrap();
+    assert_eq!(store.get_note(note.uuid).unwrap().version, 3);
+    store
+        .unarchive_note(UnarchiveNoteRequest { note_id: note.uuid })
+        .unwrap();
+    assert_eq!(store.get_note(note.uuid).unwrap().version, 4);
+}
+
+pub fn update_note_checks_version<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+    let update = |title: &str, expected_version| UpdateNoteRequest {
+        note_id: note.uuid,
+        title: Some(title.to_string()),
+        expected_version,
+        ..UpdateNoteRequest::default()
+    };
+
+    store.update_note(update("Dune", Some(1))).unwrap();
+    // Someone else still thinks the note is at version 1
+    assert!(matches!(
+        store.update_note(update("Hyperion", Some(1))),
+        Err(NotesError::Conflict(_))
+    ));
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert_eq!(stored.title, "Dune");
+    assert_eq!(stored.version, 2);
+    assert_eq!(store.get_note_history(note.uuid).unwrap().len(), 1);
+    store.update_note(update("Hyperion", Some(2))).unwrap();
+    assert_eq!(store.get_note(note.uuid).unwrap().title, "Hyperion");
+}
+
+pub fn archive_note_persists<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+
+    store
+        .archive_note(ArchiveNoteRequest { note_id: note.uuid })
+        .unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert!(stored.delete_time.is_some());
+    assert_eq!(Some(stored.last_update_time), stored.delete_time);
+}
+
+pub fn missing_notes_are_not_found<S: NoteStore>(mut store: S) {
+    let missing = Uuid::new_v4();
+    assert!(matches!(
+        store.get_note(missing),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(matches!(
+        store.update_note(UpdateNoteRequest {
+            note_id: missing,
+            title: Some("Nothing".to_string()),
+            ..UpdateNoteRequest::default()
+        }),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(matches!(
+        store.archive_note(ArchiveNoteRequest { note_id: missing }),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(matches!(
+        store.unarchive_note(UnarchiveNoteRequest { note_id: missing }),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(matches!(
+        store.purge_note(missing),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(matches!(
+        store.get_note_history(missing),
+        Err(NotesError::NotFound(_))
+    ));
+}
+
+pub fn get_notes_hides_archived_notes<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let kept = new_note(&owner, "Deep Work");
+    let archived = new_note(&owner, "Dune");
+    store.create_note(kept.clone()).unwrap();
+    store.create_note(archived.clone()).unwrap();
+    store
+        .archive_note(ArchiveNoteRequest {
+            note_id: archived.uuid,
+        })
+        .unwrap();
+
+    let titles_of = |archived| {
+        let query = NotesQuery {
+            archived,
+            sort: NoteSort::Title,
+            ..NotesQuery::default()
+        };
+        let notes = store.get_notes(owner.clone(), query).unwrap().notes;
+        titles(&notes).join(", ")
+    };
+    assert_eq!(titles_of(ArchivedNotes::Exclude), "Deep Work");
+    assert_eq!(titles_of(ArchivedNotes::Include), "Deep Work, Dune");
+    assert_eq!(titles_of(ArchivedNotes::Only), "Dune");
+}
+
+pub fn unarchive_note_restores_note<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+    store
+        .archive_note(ArchiveNoteRequest { note_id: note.uuid })
+        .unwrap();
+
+    store
+        .unarchive_note(UnarchiveNoteRequest { note_id: note.uuid })
+        .unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert_eq!(stored.delete_time, None);
+    let notes = store
+        .get_notes(note.owner, NotesQuery::default())
+        .unwrap()
+        .notes;
+    assert_eq!(titles(&notes), vec!["Deep Work"]);
+}
+
+pub fn purge_note_deletes_note<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let note = new_note(&owner, "Deep Work");
+    store.create_note(note.clone()).unwrap();
+
+    store.purge_note(note.uuid).unwrap();
+
+    assert!(matches!(
+        store.get_note(note.uuid),
+        Err(NotesError::NotFound(_))
+    ));
+    let query = NotesQuery {
+        archived: ArchivedNotes::Include,
+        ..NotesQuery::default()
+    };
+    assert!(store
+        .get_notes(owner.clone(), query)
+        .unwrap()
+        .notes
+        .is_empty());
+    assert!(store
+        .search_notes(owner, "deep".to_string())
+        .unwrap()
+        .is_empty());
+}
+
+pub fn purge_note_forgets_history<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            title: Some("Dune".to_string()),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+
+    store.purge_note(note.uuid).unwrap();
+
+    assert!(matches!(
+        store.get_note_history(note.uuid),
+        Err(NotesError::NotFound(_))
+    ));
+    // A note that comes back under the same id starts over
+    store.create_note(note.clone()).unwrap();
+    assert!(store.get_note_history(note.uuid).unwrap().is_empty());
+}
+
+pub fn purge_archived_before<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let archived_at = |title, delete_time| Note {
+        delete_time: Some(at(delete_time)),
+        ..new_note(&owner, title)
+    };
+    let expired = archived_at("Deep Work", 1609459200);
+    let recently_archived = archived_at("Dune", 1609459400);
+    let active = new_note(&owner, "Hyperion");
+    for note in vec![expired.clone(), recently_archived.clone(), active.clone()] {
+        store.create_note(note).unwrap();
+    }
+    let list = new_list(&owner, vec![expired.uuid, active.uuid]);
+    store.store_list(list.clone()).unwrap();
+    // Other checks may share the store, so only look at our own notes
+    let purge = |store: &mut S, dry_run| {
+        let purged = store
+            .purge_archived_before(at(1609459300), dry_run)
+            .unwrap();
+        purged
+            .into_iter()
+            .filter(|note| note.owner == owner)
+            .map(|note| note.uuid)
+            .collect::<Vec<Uuid>>()
+    };
+
+    assert_eq!(purge(&mut store, true), vec![expired.uuid]);
+    assert!(store.get_note(expired.uuid).is_ok());
+
+    assert_eq!(purge(&mut store, false), vec![expired.uuid]);
+    assert!(matches!(
+        store.get_note(expired.uuid),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(store.get_note(recently_archived.uuid).is_ok());
+    assert!(store.get_note(active.uuid).is_ok());
+    assert_eq!(store.get_list(list.uuid).unwrap().notes, vec![active.uuid]);
+    assert!(purge(&mut store, false).is_empty());
+}
+
+pub fn purge_note_leaves_its_lists<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let purged = new_note(&owner, "Deep Work");
+    let first = new_note(&owner, "Dune");
+    let second = new_note(&owner, "Hyperion");
+    for note in vec![purged.clone(), first.clone(), second.clone()] {
+        store.create_note(note).unwrap();
+    }
+    let list = new_list(
+        &owner,
+        vec![purged.uuid, first.uuid, purged.uuid, second.uuid],
+    );
+    let other_list = new_list(&owner, vec![second.uuid, first.uuid]);
+    store.store_list(list.clone()).unwrap();
+    store.store_list(other_list.clone()).unwrap();
+
+    store.purge_note(purged.uuid).unwrap();
+
+    assert_eq!(
+        store.get_list(list.uuid).unwrap().notes,
+        vec![first.uuid, second.uuid]
+    );
+    assert_eq!(
+        store.get_list(other_list.uuid).unwrap().notes,
+        other_list.notes
+    );
+    let full_list = store.get_full_list(list.uuid).unwrap();
+    assert_eq!(full_list.notes_in_list.len(), 2);
+}
+
+pub fn store_and_get_lists<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let first = new_note(&owner, "Deep Work");
+    let second = new_note(&owner, "Dune");
+    store.create_note(first.clone()).unwrap();
+    store.create_note(second.clone()).unwrap();
+
+    let mut list = new_list(&owner, vec![second.uuid, first.uuid]);
+    store.store_list(list.clone()).unwrap();
+
+    let full_list = store.get_full_list(list.uuid).unwrap();
+    assert_eq!(full_list.list.notes, vec![second.uuid, first.uuid]);
+    assert_eq!(full_list.notes_in_list.len(), 2);
+    assert_eq!(full_list.notes_in_list[&first.uuid].title, "Deep Work");
+
+    // Storing a list again replaces it
+    list.title = "Reading Queue".to_string();
+    list.notes = vec![first.uuid];
+    store.store_list(list.clone()).unwrap();
+
+    let lists = store.get_lists(owner).unwrap();
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[&list.uuid].title, "Reading Queue");
+    assert_eq!(lists[&list.uuid].notes, vec![first.uuid]);
+}
+
+pub fn delete_list<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let list = new_list(&owner, vec![]);
+    store.store_list(list.clone()).unwrap();
+
+    store.delete_list(list.uuid).unwrap();
+    assert!(store.get_lists(owner).unwrap().is_empty());
+    assert!(matches!(
+        store.get_list(list.uuid),
+        Err(NotesError::NotFound(_))
+    ));
+    assert!(matches!(
+        store.delete_list(list.uuid),
+        Err(NotesError::NotFound(_))
+    ));
+}
+
+pub fn create_note_without_tags<S: NoteStore>(mut store: S) {
+    let mut note = new_note(&new_owner(), "Untitled");
+    note.tags = HashSet::new();
+    note.description = String::new();
+    store.create_note(note.clone()).unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert!(stored.tags.is_empty());
+    assert_eq!(stored.description, "");
+}
+
+pub fn tag_payloads_round_trip<S: NoteStore>(mut store: S) {
+    let mut note = new_note(&new_owner(), "Amélie");
+    note.tags = vec![
+        Tag::Movie,
+        Tag::RecommendedBy("Zoë \"Z\" O'Brien, {friend}".to_string()),
+        Tag::RecommendedBy("Bob".to_string()),
+        Tag::RemindsMeOf("Paris, 2019".to_string()),
+        Tag::TalkedTo("Bob".to_string()),
+        Tag::Origin(String::new()),
+    ]
+    .into_iter()
+    .collect();
+    store.create_note(note.clone()).unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert_eq!(stored.title, "Amélie");
+    assert_eq!(stored.tags, note.tags);
+}
+
+pub fn get_notes_for_unknown_owner<S: NoteStore>(store: S) {
+    let page = store.get_notes(new_owner(), NotesQuery::default()).unwrap();
+    assert!(page.notes.is_empty());
+    assert_eq!(page.next_cursor, None);
+}
+
+pub fn empty_update_keeps_fields<S: NoteStore>(mut store: S) {
+    let note = new_note(&new_owner(), "Deep Work");
+    store.create_note(note.clone()).unwrap();
+
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+
+    let stored = store.get_note(note.uuid).unwrap();
+    assert_eq!(stored.title, note.title);
+    assert_eq!(stored.description, note.description);
+    assert_eq!(stored.tags, note.tags);
+    assert_eq!(stored.status, note.status);
+    assert_eq!(stored.create_time, note.create_time);
+    assert_eq!(stored.delete_time, None);
+}
+
+pub fn empty_list<S: NoteStore>(mut store: S) {
+    let list = new_list(&new_owner(), vec![]);
+    store.store_list(list.clone()).unwrap();
+
+    let full_list = store.get_full_list(list.uuid).unwrap();
+    assert!(full_list.list.notes.is_empty());
+    assert!(full_list.notes_in_list.is_empty());
+    assert_eq!(full_list.list.title, list.title);
+    assert_eq!(full_list.list.description, list.description);
+}
+
+pub fn list_can_repeat_notes<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let note = new_note(&owner, "Deep Work");
+    store.create_note(note.clone()).unwrap();
+
+    let list = new_list(&owner, vec![note.uuid, note.uuid]);
+    store.store_list(list.clone()).unwrap();
+
+    let full_list = store.get_full_list(list.uuid).unwrap();
+    assert_eq!(full_list.list.notes, vec![note.uuid, note.uuid]);
+    assert_eq!(full_list.notes_in_list.len(), 1);
+}
+
+pub fn list_keeps_archived_notes<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let note = new_note(&owner, "Deep Work");
+    store.create_note(note.clone()).unwrap();
+    let list = new_list(&owner, vec![note.uuid]);
+    store.store_list(list.clone()).unwrap();
+
+    store
+        .archive_note(ArchiveNoteRequest { note_id: note.uuid })
+        .unwrap();
+
+    let full_list = store.get_full_list(list.uuid).unwrap();
+    assert!(full_list.notes_in_list[&note.uuid].delete_time.is_some());
+}
+
+pub fn list_with_missing_note<S: NoteStore>(mut store: S) {
+    let list = new_list(&new_owner(), vec![Uuid::new_v4()]);
+    store.store_list(list.clone()).unwrap();
+
+    // The list itself is fine, it just can't be filled in
+    assert_eq!(store.get_list(list.uuid).unwrap().notes, list.notes);
+    assert!(matches!(
+        store.get_full_list(list.uuid),
+        Err(NotesError::NotFound(_))
+    ));
+}
+
+pub fn search_notes<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let mut in_title = new_note(&owner, "Deep Work");
+    in_title.description = "Rules for focused success".to_string();
+    let mut in_description = new_note(&owner, "Digital Minimalism");
+    in_description.description = "More deep work, with fewer screens".to_string();
+    let mut one_word = new_note(&owner, "Deep Space Nine");
+    one_word.description = "A show".to_string();
+    for note in vec![in_title.clone(), in_description.clone(), one_word] {
+        store.create_note(note).unwrap();
+    }
+
+    // Every word has to match, and matches in the title come first
+    let found = store
+        .search_notes(owner.clone(), "WORK deep".to_string())
+        .unwrap();
+    let titles: Vec<&str> = found.iter().map(|note| note.title.as_str()).collect();
+    assert_eq!(titles, vec!["Deep Work", "Digital Minimalism"]);
+
+    let found = store
+        .search_notes(owner.clone(), "focused".to_string())
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].uuid, in_title.uuid);
+    assert_eq!(found[0].tags, in_title.tags);
+
+    assert!(store
+        .search_notes(owner.clone(), "deep nonexistent".to_string())
+        .unwrap()
+        .is_empty());
+    // Nothing in the query is treated as syntax
+    assert!(store
+        .search_notes(owner, "\"deep\" OR -(work) *".to_string())
+        .is_ok());
+}
+
+pub fn search_sees_updates<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    let note = new_note(&owner, "Deep Work");
+    store.create_note(note.clone()).unwrap();
+
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            title: Some("Hyperion".to_string()),
+            description: Some("Shrike".to_string()),
+            tags: None,
+            status: None,
+            expected_version: None,
+        })
+        .unwrap();
+
+    assert!(store
+        .search_notes(owner.clone(), "deep".to_string())
+        .unwrap()
+        .is_empty());
+    let found = store.search_notes(owner, "shrike".to_string()).unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].title, "Hyperion");
+}
+
+pub fn search_only_returns_owners_notes<S: NoteStore>(mut store: S) {
+    let owner = new_owner();
+    store.create_note(new_note(&owner, "Hyperion")).unwrap();
+    store
+        .create_note(new_note(&new_owner(), "Hyperion"))
+        .unwrap();
+
+    let found = store.search_notes(owner, "hyperion".to_string()).unwrap();
+    assert_eq!(found.len(), 1);
+}
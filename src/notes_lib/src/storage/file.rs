@@ -0,0 +1,388 @@
+use super::memory::{self, MemoryNoteStore};
+use super::traits::NoteStore;
+use crate::error::Result;
+use crate::timestamp;
+use crate::types::{
+    ArchiveNoteRequest, FullList, List, Note, NoteRevision, NotesPage, NotesQuery, Person, Tag,
+    TagDefinition, TagFacet, UnarchiveNoteRequest, UpdateNoteRequest,
+};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+const LOG_FILE: &str = "notes.jsonl";
+/// Number of events appended to the log before it is compacted
+const DEFAULT_COMPACT_EVERY: usize = 1000;
+
+/// Every change to a `FileNoteStore` is appended to its log as one of these.
+/// Events record the resulting state rather than the request, so replaying
+/// them doesn't depend on when they are replayed.
+#[derive(Debug, Serialize, Deserialize)]
+enum Event {
+    PutNote(Note),
+    PutList(List),
+    DeleteList(Uuid),
+    PurgeNote(Uuid),
+    PutRevision(NoteRevision),
+    PutTagDefinition(TagDefinition),
+    PutPerson(Person),
+    /// Changes that only make sense together, e.g. every note of a rename.
+    /// They share a line of the log, so a torn write loses all of them
+    Batch(Vec<Event>),
+}
+
+struct EventLog {
+    path: PathBuf,
+    file: File,
+    /// Events appended since the log was last compacted
+    appended: usize,
+    compact_every: usize,
+}
+
+/// Stores notes and lists in a single append-only JSONL file in `data_dir`.
+/// Everything is also kept in memory, which is where reads are served from.
+/// Changes are only made in memory once they have been synced to the log, so
+/// anything a read sees survives a restart.
+#[derive(Clone)]
+pub struct FileNoteStore {
+    memory: MemoryNoteStore,
+    log: Arc<Mutex<EventLog>>,
+}
+
+impl FileNoteStore {
+    /// Open the log in `data_dir`, creating it if needed, and replay it into memory
+    pub fn new(data_dir: &Path) -> FileNoteStore {
+        Self::with_compaction(data_dir, DEFAULT_COMPACT_EVERY)
+    }
+
+    /// Like `new`, but compact the log every `compact_every` events
+    pub fn with_compaction(data_dir: &Path, compact_every: usize) -> FileNoteStore {
+        fs::create_dir_all(data_dir).expect("Could not create data directory");
+        let path = data_dir.join(LOG_FILE);
+        let mut memory = MemoryNoteStore::new();
+        if path.exists() {
+            replay(&path, &mut memory).expect("Could not replay notes log");
+        }
+        let file = open_log(&path).expect("Could not open notes log");
+        let store = FileNoteStore {
+            memory,
+            log: Arc::new(Mutex::new(EventLog {
+                path,
+                file,
+                appended: 0,
+                compact_every,
+            })),
+        };
+        // Start from a compact log, so replays stay quick across restarts
+        let mut log = store.log.lock().unwrap();
+        store
+            .compact(&mut log)
+            .expect("Could not compact notes log");
+        drop(log);
+        store
+    }
+
+    /// Sync `events` to the log as a single line, then make them in memory.
+    /// The log is compacted afterwards if it has grown enough
+    fn commit(&self, log: &mut EventLog, mut events: Vec<Event>) -> Result<()> {
+        let event = match events.len() {
+            0 => return Ok(()),
+            1 => events.remove(0),
+            _ => Event::Batch(events),
+        };
+        let mut line = serde_json::to_string(&event).context("Serializing event")?;
+        line.push('\n');
+        let length = log
+            .file
+            .metadata()
+            .context(format!("Reading {}", log.path.display()))?
+            .len();
+        let written = log
+            .file
+            .write_all(line.as_bytes())
+            .and_then(|_| log.file.sync_data());
+        if let Err(err) = written {
+            // Cut off whatever did make it, so the next event doesn't follow a torn line
+            if let Err(truncate_err) = log.file.set_len(length) {
+                warn!(
+                    "Could not truncate {}: {}",
+                    log.path.display(),
+                    truncate_err
+                );
+            }
+            return Err(anyhow::Error::new(err)
+                .context(format!("Appending to {}", log.path.display()))
+                .into());
+        }
+        // Shares everything with `self.memory`
+        apply(&mut self.memory.clone(), event)?;
+        log.appended += 1;
+        if log.appended >= log.compact_every {
+            // The change is safe in the log either way, so this doesn't fail it
+            if let Err(err) = self.compact(log) {
+                warn!("Could not compact {}: {:#}", log.path.display(), err);
+            }
+        }
+        Ok(())
+    }
+
+    /// The number the next revision of a note gets
+    fn next_revision(&self, id: Uuid) -> u32 {
+        self.memory
+            .latest_revision(id)
+            .map_or(1, |revision| revision.revision + 1)
+    }
+
+    /// Rewrite the log so that it only holds the current notes, their history, lists,
+    /// custom tags and people.
+    /// The new log is written next to the old one and renamed over it.
+    fn compact(&self, log: &mut EventLog) -> anyhow::Result<()> {
+        let tmp_path = log.path.with_extension("jsonl.tmp");
+        let mut tmp =
+            File::create(&tmp_path).context(format!("Creating {}", tmp_path.display()))?;
+        let mut events = 0;
+        for note in self.memory.all_notes() {
+            writeln!(tmp, "{}", serde_json::to_string(&Event::PutNote(note))?)?;
+            events += 1;
+        }
+        for revision in self.memory.all_revisions() {
+            writeln!(
+                tmp,
+                "{}",
+                serde_json::to_string(&Event::PutRevision(revision))?
+            )?;
+            events += 1;
+        }
+        for list in self.memory.all_lists() {
+            writeln!(tmp, "{}", serde_json::to_string(&Event::PutList(list))?)?;
+            events += 1;
+        }
+        for definition in self.memory.all_tag_definitions() {
+            writeln!(
+                tmp,
+                "{}",
+                serde_json::to_string(&Event::PutTagDefinition(definition))?
+            )?;
+            events += 1;
+        }
+        for person in self.memory.all_people() {
+            writeln!(tmp, "{}", serde_json::to_string(&Event::PutPerson(person))?)?;
+            events += 1;
+        }
+        tmp.sync_all().context("Syncing compacted log")?;
+        fs::rename(&tmp_path, &log.path).context("Replacing log with compacted log")?;
+        log.file = open_log(&log.path)?;
+        log.appended = 0;
+        info!("Compacted {} to {} events", log.path.display(), events);
+        Ok(())
+    }
+}
+
+fn open_log(path: &Path) -> anyhow::Result<File> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("Opening {}", path.display()))
+}
+
+fn replay(path: &Path, memory: &mut MemoryNoteStore) -> anyhow::Result<()> {
+    let file = File::open(path).context(format!("Opening {}", path.display()))?;
+    let lines = BufReader::new(file)
+        .lines()
+        .collect::<std::io::Result<Vec<String>>>()
+        .context(format!("Reading {}", path.display()))?;
+    let last = lines.len();
+    for (number, line) in lines.into_iter().enumerate() {
+        let event = match serde_json::from_str::<Event>(&line) {
+            Ok(event) => event,
+            // A crash mid-write can only tear the final line, and its change was never acknowledged
+            Err(err) if number + 1 == last => {
+                warn!("Skipping torn last line of {}: {}", path.display(), err);
+                break;
+            }
+            Err(err) => {
+                return Err(anyhow!(
+                    "Line {} of {} is not an event: {}",
+                    number + 1,
+                    path.display(),
+                    err
+                ))
+            }
+        };
+        apply(memory, event)?;
+    }
+    Ok(())
+}
+
+/// Make the change an event records
+fn apply(memory: &mut MemoryNoteStore, event: Event) -> Result<()> {
+    match event {
+        Event::PutNote(note) => memory.create_note(note)?,
+        Event::PutList(list) => memory.store_list(list)?,
+        Event::DeleteList(id) => memory.delete_list(id)?,
+        Event::PurgeNote(id) => memory.purge_note(id)?,
+        Event::PutRevision(revision) => memory.put_revision(revision),
+        Event::PutTagDefinition(definition) => memory.store_tag_definition(definition)?,
+        Event::PutPerson(person) => memory.store_person(person)?,
+        Event::Batch(events) => {
+            for event in events {
+                apply(memory, event)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl NoteStore for FileNoteStore {
+    fn get_note(&self, id: Uuid) -> Result<Note> {
+        self.memory.get_note(id)
+    }
+
+    fn get_notes(&self, owner: String, query: NotesQuery) -> Result<NotesPage> {
+        self.memory.get_notes(owner, query)
+    }
+
+    fn search_notes(&self, owner: String, query: String) -> Result<Vec<Note>> {
+        self.memory.search_notes(owner, query)
+    }
+
+    fn tag_facets(&self, owner: String) -> Result<Vec<TagFacet>> {
+        self.memory.tag_facets(owner)
+    }
+
+    fn create_note(&mut self, note: Note) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        self.commit(&mut log, vec![Event::PutNote(note)])
+    }
+
+    fn update_note(&mut self, request: UpdateNoteRequest) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        let mut note = self.memory.get_note(request.note_id)?;
+        memory::check_version(&note, request.expected_version)?;
+        let revision = NoteRevision::of(&note, self.next_revision(note.uuid));
+        memory::apply_update(&mut note, request);
+        self.commit(
+            &mut log,
+            vec![Event::PutRevision(revision), Event::PutNote(note)],
+        )
+    }
+
+    fn rename_tag(
+        &mut self,
+        owner: String,
+        from: Tag,
+        to: Tag,
+        dry_run: bool,
+    ) -> Result<Vec<Note>> {
+        let mut log = self.log.lock().unwrap();
+        let renamed = self
+            .memory
+            .rename_tag(owner, from.clone(), to.clone(), true)?;
+        if !dry_run {
+            let now = timestamp::now();
+            let mut events = Vec::new();
+            for note in &renamed {
+                events.push(Event::PutRevision(NoteRevision::of(
+                    note,
+                    self.next_revision(note.uuid),
+                )));
+                let mut note = note.clone();
+                memory::retag(&mut note, &from, &to, now);
+                events.push(Event::PutNote(note));
+            }
+            self.commit(&mut log, events)?;
+        }
+        Ok(renamed)
+    }
+
+    fn get_note_history(&self, id: Uuid) -> Result<Vec<NoteRevision>> {
+        self.memory.get_note_history(id)
+    }
+
+    fn archive_note(&mut self, request: ArchiveNoteRequest) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        let mut note = self.memory.get_note(request.note_id)?;
+        memory::set_archived(&mut note, true);
+        self.commit(&mut log, vec![Event::PutNote(note)])
+    }
+
+    fn unarchive_note(&mut self, request: UnarchiveNoteRequest) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        let mut note = self.memory.get_note(request.note_id)?;
+        memory::set_archived(&mut note, false);
+        self.commit(&mut log, vec![Event::PutNote(note)])
+    }
+
+    fn purge_note(&mut self, id: Uuid) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        self.memory.get_note(id)?;
+        // Replaying this takes the note out of its lists again
+        self.commit(&mut log, vec![Event::PurgeNote(id)])
+    }
+
+    fn purge_archived_before(&mut self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<Vec<Note>> {
+        let mut log = self.log.lock().unwrap();
+        let purged = self.memory.purge_archived_before(cutoff, true)?;
+        if !dry_run {
+            let events = purged
+                .iter()
+                .map(|note| Event::PurgeNote(note.uuid))
+                .collect();
+            self.commit(&mut log, events)?;
+        }
+        Ok(purged)
+    }
+
+    fn get_list(&self, id: Uuid) -> Result<List> {
+        self.memory.get_list(id)
+    }
+
+    fn get_lists(&self, owner: String) -> Result<HashMap<Uuid, List>> {
+        self.memory.get_lists(owner)
+    }
+
+    fn get_full_list(&self, id: Uuid) -> Result<FullList> {
+        self.memory.get_full_list(id)
+    }
+
+    fn store_list(&mut self, list: List) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        self.commit(&mut log, vec![Event::PutList(list)])
+    }
+
+    fn delete_list(&mut self, id: Uuid) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        self.memory.get_list(id)?;
+        self.commit(&mut log, vec![Event::DeleteList(id)])
+    }
+
+    fn get_tag_definitions(&self, owner: String) -> Result<Vec<TagDefinition>> {
+        self.memory.get_tag_definitions(owner)
+    }
+
+    fn store_tag_definition(&mut self, definition: TagDefinition) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        self.commit(&mut log, vec![Event::PutTagDefinition(definition)])
+    }
+
+    fn get_people(&self, owner: String) -> Result<Vec<Person>> {
+        self.memory.get_people(owner)
+    }
+
+    fn get_person(&self, id: Uuid) -> Result<Person> {
+        self.memory.get_person(id)
+    }
+
+    fn store_person(&mut self, person: Person) -> Result<()> {
+        let mut log = self.log.lock().unwrap();
+        self.commit(&mut log, vec![Event::PutPerson(person)])
+    }
+}
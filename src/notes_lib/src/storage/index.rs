@@ -0,0 +1,78 @@
+use crate::types::Note;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+/// How much more a word in the title counts than a word in the description
+const TITLE_WEIGHT: u32 = 10;
+
+/// Split text into the lowercase words we search by
+pub(crate) fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// An inverted index over the titles and descriptions of notes.
+/// Unlike the database backends, words are matched exactly, without stemming.
+#[derive(Default)]
+pub(crate) struct SearchIndex {
+    /// word -> note -> how strongly that note is about the word
+    postings: HashMap<String, HashMap<Uuid, u32>>,
+    /// note -> the words it is indexed under, so it can be removed again
+    words: HashMap<Uuid, HashSet<String>>,
+}
+
+impl SearchIndex {
+    /// Index a note, replacing whatever was indexed for it before
+    pub(crate) fn insert(&mut self, note: &Note) {
+        self.remove(note.uuid);
+        let mut weights: HashMap<String, u32> = HashMap::new();
+        for word in tokenize(&note.title) {
+            *weights.entry(word).or_default() += TITLE_WEIGHT;
+        }
+        for word in tokenize(&note.description) {
+            *weights.entry(word).or_default() += 1;
+        }
+        for (word, weight) in &weights {
+            self.postings
+                .entry(word.clone())
+                .or_default()
+                .insert(note.uuid, *weight);
+        }
+        self.words.insert(note.uuid, weights.into_keys().collect());
+    }
+
+    pub(crate) fn remove(&mut self, id: Uuid) {
+        for word in self.words.remove(&id).unwrap_or_default() {
+            if let Some(notes) = self.postings.get_mut(&word) {
+                notes.remove(&id);
+                if notes.is_empty() {
+                    self.postings.remove(&word);
+                }
+            }
+        }
+    }
+
+    /// Notes containing every word of `query`, with their scores
+    pub(crate) fn search(&self, query: &str) -> HashMap<Uuid, u32> {
+        let mut words = tokenize(query);
+        words.sort();
+        words.dedup();
+        let mut matches: Option<HashMap<Uuid, u32>> = None;
+        for word in words {
+            let notes = match self.postings.get(&word) {
+                Some(notes) => notes,
+                None => return HashMap::new(),
+            };
+            matches = Some(match matches {
+                None => notes.clone(),
+                Some(so_far) => so_far
+                    .into_iter()
+                    .filter_map(|(id, score)| notes.get(&id).map(|weight| (id, score + weight)))
+                    .collect(),
+            });
+        }
+        matches.unwrap_or_default()
+    }
+}
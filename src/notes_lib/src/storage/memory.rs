@@ -1,14 +1,45 @@
+use super::index::SearchIndex;
 use super::traits::NoteStore;
-use crate::types::{ArchiveNoteRequest, FullList, List, Note, UpdateNoteRequest};
-use anyhow::{Context, Result};
-use std::collections::HashMap;
+use crate::error::{NotesError, Result};
+use crate::timestamp;
+use crate::types::{
+    ArchiveNoteRequest, FullList, List, Note, NoteRevision, NotesPage, NotesQuery, Person, Tag,
+    TagDefinition, TagFacet, UnarchiveNoteRequest, UpdateNoteRequest,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
 use std::sync::{Arc, RwLock};
 use uuid::Uuid;
 
+/// Everything in a `MemoryNoteStore`, as written to a snapshot file
+#[derive(Default, Deserialize, Serialize)]
+struct Snapshot {
+    notes: Vec<Note>,
+    lists: Vec<List>,
+    /// Snapshots from before notes had a history have no revisions
+    #[serde(default)]
+    revisions: Vec<NoteRevision>,
+    #[serde(default)]
+    tag_definitions: Vec<TagDefinition>,
+    #[serde(default)]
+    people: Vec<Person>,
+}
+
 #[derive(Clone)]
 pub struct MemoryNoteStore {
     note_storage: Arc<RwLock<HashMap<Uuid, Note>>>,
     list_storage: Arc<RwLock<HashMap<Uuid, List>>>,
+    search_index: Arc<RwLock<SearchIndex>>,
+    /// Past versions of every note, oldest first
+    revisions: Arc<RwLock<HashMap<Uuid, Vec<NoteRevision>>>>,
+    /// Custom tags, by owner and then name
+    tag_definitions: Arc<RwLock<HashMap<String, BTreeMap<String, TagDefinition>>>>,
+    people: Arc<RwLock<HashMap<Uuid, Person>>>,
 }
 
 impl MemoryNoteStore {
@@ -16,8 +47,178 @@ impl MemoryNoteStore {
         MemoryNoteStore {
             note_storage: Arc::new(RwLock::new(HashMap::new())),
             list_storage: Arc::new(RwLock::new(HashMap::new())),
+            search_index: Arc::new(RwLock::new(SearchIndex::default())),
+            revisions: Arc::new(RwLock::new(HashMap::new())),
+            tag_definitions: Arc::new(RwLock::new(HashMap::new())),
+            people: Arc::new(RwLock::new(HashMap::new())),
         }
     }
+
+    /// Load a store from the snapshot at `path`. A missing snapshot gives an empty store
+    pub fn load_snapshot(path: &Path) -> anyhow::Result<MemoryNoteStore> {
+        let mut store = MemoryNoteStore::new();
+        if !path.exists() {
+            return Ok(store);
+        }
+        let file = File::open(path).context(format!("Opening {}", path.display()))?;
+        let snapshot: Snapshot = serde_json::from_reader(BufReader::new(file))
+            .context(format!("Reading snapshot {}", path.display()))?;
+        {
+            let mut notes = store.note_storage.write().unwrap();
+            let mut search_index = store.search_index.write().unwrap();
+            for note in snapshot.notes {
+                search_index.insert(&note);
+                notes.insert(note.uuid, note);
+            }
+            let mut lists = store.list_storage.write().unwrap();
+            for list in snapshot.lists {
+                lists.insert(list.uuid, list);
+            }
+        }
+        for revision in snapshot.revisions {
+            store.put_revision(revision);
+        }
+        for definition in snapshot.tag_definitions {
+            store.store_tag_definition(definition)?;
+        }
+        for person in snapshot.people {
+            store.store_person(person)?;
+        }
+        Ok(store)
+    }
+
+    /// Write everything in the store to `path`. The snapshot is written next to
+    /// `path` and renamed over it, so a crash never leaves a partial snapshot behind.
+    pub fn dump_snapshot(&self, path: &Path) -> anyhow::Result<()> {
+        let snapshot = Snapshot {
+            notes: self.all_notes(),
+            lists: self.all_lists(),
+            revisions: self.all_revisions(),
+            tag_definitions: self.all_tag_definitions(),
+            people: self.all_people(),
+        };
+        let tmp_path = path.with_extension("tmp");
+        let file = File::create(&tmp_path).context(format!("Creating {}", tmp_path.display()))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, &snapshot).context("Writing snapshot")?;
+        writer.flush().context("Writing snapshot")?;
+        writer.get_ref().sync_all().context("Syncing snapshot")?;
+        fs::rename(&tmp_path, path).context(format!("Replacing {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Every note in the store, regardless of owner
+    pub(crate) fn all_notes(&self) -> Vec<Note> {
+        self.note_storage
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Every list in the store, regardless of owner
+    pub(crate) fn all_lists(&self) -> Vec<List> {
+        self.list_storage
+            .read()
+            .unwrap()
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Every revision of every note in the store
+    pub(crate) fn all_revisions(&self) -> Vec<NoteRevision> {
+        self.revisions
+            .read()
+            .unwrap()
+            .values()
+            .flatten()
+            .cloned()
+            .collect()
+    }
+
+    /// Every person, of every owner
+    pub(crate) fn all_people(&self) -> Vec<Person> {
+        self.people.read().unwrap().values().cloned().collect()
+    }
+
+    /// Every custom tag, of every owner
+    pub(crate) fn all_tag_definitions(&self) -> Vec<TagDefinition> {
+        self.tag_definitions
+            .read()
+            .unwrap()
+            .values()
+            .flat_map(|definitions| definitions.values())
+            .cloned()
+            .collect()
+    }
+
+    /// The revision the last update of a note recorded, if it was ever updated
+    pub(crate) fn latest_revision(&self, id: Uuid) -> Option<NoteRevision> {
+        self.revisions
+            .read()
+            .unwrap()
+            .get(&id)
+            .and_then(|revisions| revisions.last())
+            .cloned()
+    }
+
+    /// Add a revision to the history of its note, as when loading it back from disk
+    pub(crate) fn put_revision(&self, revision: NoteRevision) {
+        let mut revisions = self.revisions.write().unwrap();
+        let history = revisions.entry(revision.note_id).or_default();
+        history.retain(|other| other.revision != revision.revision);
+        history.push(revision);
+        history.sort_by_key(|revision| revision.revision);
+    }
+}
+
+/// Fail unless `note` is still at the version an update expects, if it expects one
+pub(crate) fn check_version(note: &Note, expected_version: Option<u64>) -> Result<()> {
+    match expected_version {
+        Some(expected_version) if note.version != expected_version => {
+            Err(NotesError::Conflict(format!(
+                "Note {} is at version {}, not {}",
+                note.uuid, note.version, expected_version
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Make the changes `request` asks for, as the next version of `note`
+pub(crate) fn apply_update(note: &mut Note, request: UpdateNoteRequest) {
+    if let Some(title) = request.title {
+        note.title = title;
+    }
+    if let Some(description) = request.description {
+        note.description = description;
+    }
+    if let Some(tags) = request.tags {
+        note.tags = tags;
+    }
+    if let Some(status) = request.status {
+        note.status = status;
+    }
+    note.last_update_time = timestamp::now();
+    note.version += 1;
+}
+
+/// Swap `from` for `to` in the tags of `note`, as its next version
+pub(crate) fn retag(note: &mut Note, from: &Tag, to: &Tag, now: DateTime<Utc>) {
+    note.tags.remove(from);
+    note.tags.insert(to.clone());
+    note.last_update_time = now;
+    note.version += 1;
+}
+
+/// Move `note` into or out of the trash, as its next version
+pub(crate) fn set_archived(note: &mut Note, archived: bool) {
+    let now = timestamp::now();
+    note.last_update_time = now;
+    note.delete_time = if archived { Some(now) } else { None };
+    note.version += 1;
 }
 
 impl NoteStore for MemoryNoteStore {
@@ -26,57 +227,191 @@ impl NoteStore for MemoryNoteStore {
             .read()
             .unwrap()
             .get(&id)
-            .context(format!("Looking for note with id {}", id))
-            .map(|x| x.clone())
+            .cloned()
+            .ok_or_else(|| NotesError::NotFound(format!("No note found for id {}", id)))
     }
 
-    fn get_notes(&self, owner: String) -> Result<HashMap<Uuid, Note>> {
-        let mut map = HashMap::new();
+    fn get_notes(&self, owner: String, query: NotesQuery) -> Result<NotesPage> {
         let storage = self.note_storage.read().unwrap();
-        for note in storage.values() {
-            if note.owner == owner {
-                map.insert(note.uuid.clone(), note.clone());
-            }
-        }
-        Ok(map)
+        let mut notes: Vec<&Note> = storage
+            .values()
+            .filter(|note| note.owner == owner && query.includes(note))
+            .collect();
+        notes.sort_by(|note, other| query.sort.key(note).cmp(&query.sort.key(other)));
+        // One more than asked for tells NotesPage whether there is a next page
+        let notes = match query.limit {
+            Some(limit) => notes.into_iter().take(limit + 1).cloned().collect(),
+            None => notes.into_iter().cloned().collect(),
+        };
+        Ok(NotesPage::new(notes, query.sort, query.limit))
+    }
+
+    fn search_notes(&self, owner: String, query: String) -> Result<Vec<Note>> {
+        // Let go of the index before touching notes, writers lock them the other way around
+        let matches = self.search_index.read().unwrap().search(&query);
+        let storage = self.note_storage.read().unwrap();
+        let mut found: Vec<(u32, Note)> = matches
+            .into_iter()
+            .filter_map(|(id, score)| storage.get(&id).map(|note| (score, note)))
+            .filter(|(_, note)| note.owner == owner)
+            .map(|(score, note)| (score, note.clone()))
+            .collect();
+        found.sort_by(|(score, note), (other_score, other_note)| {
+            other_score
+                .cmp(score)
+                .then_with(|| note.title.cmp(&other_note.title))
+                .then_with(|| note.uuid.cmp(&other_note.uuid))
+        });
+        Ok(found.into_iter().map(|(_, note)| note).collect())
+    }
+
+    fn tag_facets(&self, owner: String) -> Result<Vec<TagFacet>> {
+        let storage = self.note_storage.read().unwrap();
+        Ok(TagFacet::count(
+            storage
+                .values()
+                .filter(|note| note.owner == owner && note.delete_time.is_none())
+                .map(|note| &note.tags),
+        ))
     }
 
     fn create_note(&mut self, note: Note) -> Result<()> {
-        self.note_storage
-            .write()
-            .unwrap()
-            .insert(note.uuid.clone(), note);
+        let mut storage = self.note_storage.write().unwrap();
+        self.search_index.write().unwrap().insert(&note);
+        storage.insert(note.uuid.clone(), note);
         Ok(())
     }
 
     fn update_note(&mut self, request: UpdateNoteRequest) -> Result<()> {
-        // Make Updates for all fields of UpdateNoteRequest
-        // Get note from storage
-        let mut note = self
-            .get_note(request.note_id)
-            .context("getting note to update")?;
-
-        if let Some(title) = request.title {
-            note.title = title;
-        }
-        if let Some(description) = request.description {
-            note.description = description;
+        // Make Updates for all fields of UpdateNoteRequest in place, so they persist
+        let mut storage = self.note_storage.write().unwrap();
+        let note = storage.get_mut(&request.note_id).ok_or_else(|| {
+            NotesError::NotFound(format!("No note found for id {}", request.note_id))
+        })?;
+        check_version(note, request.expected_version)?;
+
+        let mut revisions = self.revisions.write().unwrap();
+        let history = revisions.entry(note.uuid).or_default();
+        history.push(NoteRevision::of(note, history.len() as u32 + 1));
+
+        apply_update(note, request);
+        self.search_index.write().unwrap().insert(note);
+        Ok(())
+    }
+
+    fn rename_tag(
+        &mut self,
+        owner: String,
+        from: Tag,
+        to: Tag,
+        dry_run: bool,
+    ) -> Result<Vec<Note>> {
+        // Hold on to the notes throughout, so every note is retagged at once
+        let mut storage = self.note_storage.write().unwrap();
+        let mut revisions = self.revisions.write().unwrap();
+        let now = timestamp::now();
+        let mut renamed = Vec::new();
+        for note in storage
+            .values_mut()
+            .filter(|note| note.owner == owner && note.tags.contains(&from))
+        {
+            renamed.push(note.clone());
+            if dry_run {
+                continue;
+            }
+            let history = revisions.entry(note.uuid).or_default();
+            history.push(NoteRevision::of(note, history.len() as u32 + 1));
+            retag(note, &from, &to, now);
         }
-        if let Some(tags) = request.tags {
-            note.tags = tags.into();
+        Ok(renamed)
+    }
+
+    fn get_note_history(&self, id: Uuid) -> Result<Vec<NoteRevision>> {
+        let storage = self.note_storage.read().unwrap();
+        if !storage.contains_key(&id) {
+            return Err(NotesError::NotFound(format!("No note found for id {}", id)));
         }
-        note.last_update_time = format!("{}", chrono::offset::Utc::now().timestamp());
-        Ok(())
+        Ok(self
+            .revisions
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .unwrap_or_default())
     }
 
     fn archive_note(&mut self, archive_request: ArchiveNoteRequest) -> Result<()> {
-        let mut note = self
-            .get_note(archive_request.note_id)
-            .context("getting note to update")?;
-        note.delete_time = Some(format!("{}", chrono::offset::Utc::now().timestamp()));
+        let mut storage = self.note_storage.write().unwrap();
+        let note = storage.get_mut(&archive_request.note_id).ok_or_else(|| {
+            NotesError::NotFound(format!("No note found for id {}", archive_request.note_id))
+        })?;
+        set_archived(note, true);
         Ok(())
     }
 
+    fn unarchive_note(&mut self, unarchive_request: UnarchiveNoteRequest) -> Result<()> {
+        let mut storage = self.note_storage.write().unwrap();
+        let note = storage.get_mut(&unarchive_request.note_id).ok_or_else(|| {
+            NotesError::NotFound(format!(
+                "No note found for id {}",
+                unarchive_request.note_id
+            ))
+        })?;
+        set_archived(note, false);
+        Ok(())
+    }
+
+    fn purge_note(&mut self, id: Uuid) -> Result<()> {
+        let mut storage = self.note_storage.write().unwrap();
+        storage
+            .remove(&id)
+            .ok_or_else(|| NotesError::NotFound(format!("No note found for id {}", id)))?;
+        self.search_index.write().unwrap().remove(id);
+        self.revisions.write().unwrap().remove(&id);
+        for list in self.list_storage.write().unwrap().values_mut() {
+            list.notes.retain(|note_id| *note_id != id);
+        }
+        Ok(())
+    }
+
+    fn purge_archived_before(&mut self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<Vec<Note>> {
+        // Hold on to the notes throughout, so nothing is unarchived while we purge it
+        let mut storage = self.note_storage.write().unwrap();
+        let expired: Vec<Note> = storage
+            .values()
+            .filter(|note| {
+                note.delete_time
+                    .map_or(false, |delete_time| delete_time < cutoff)
+            })
+            .cloned()
+            .collect();
+        if dry_run || expired.is_empty() {
+            return Ok(expired);
+        }
+        let mut search_index = self.search_index.write().unwrap();
+        let mut revisions = self.revisions.write().unwrap();
+        let mut expired_ids = HashSet::new();
+        for note in &expired {
+            storage.remove(&note.uuid);
+            search_index.remove(note.uuid);
+            revisions.remove(&note.uuid);
+            expired_ids.insert(note.uuid);
+        }
+        for list in self.list_storage.write().unwrap().values_mut() {
+            list.notes.retain(|note_id| !expired_ids.contains(note_id));
+        }
+        Ok(expired)
+    }
+
+    fn get_list(&self, id: Uuid) -> Result<List> {
+        self.list_storage
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| NotesError::NotFound(format!("No list found for id {}", id)))
+    }
+
     fn get_lists(&self, owner: String) -> Result<HashMap<Uuid, List>> {
         let mut map = HashMap::new();
         let storage = self.list_storage.read().unwrap();
@@ -89,18 +424,10 @@ impl NoteStore for MemoryNoteStore {
     }
     fn get_full_list(&self, id: Uuid) -> Result<FullList> {
         let mut notes_in_list = HashMap::new();
-        let list = self
-            .list_storage
-            .read()
-            .unwrap()
-            .get(&id)
-            .context(format!("Looking for list with id {}", id))
-            .map(|x| x.clone())?;
+        let list = self.get_list(id)?;
         for note_id in &list.notes {
             // TODO --> What happens if I have a deleted note in a list?
-            let note = self
-                .get_note(*note_id)
-                .context("Looking for note in list")?;
+            let note = self.get_note(*note_id)?;
             notes_in_list.insert(note_id.clone(), note);
         }
         Ok(FullList {
@@ -115,4 +442,65 @@ impl NoteStore for MemoryNoteStore {
             .insert(list.uuid.clone(), list);
         Ok(())
     }
+
+    fn delete_list(&mut self, id: Uuid) -> Result<()> {
+        self.list_storage
+            .write()
+            .unwrap()
+            .remove(&id)
+            .ok_or_else(|| NotesError::NotFound(format!("No list found for id {}", id)))?;
+        Ok(())
+    }
+
+    fn get_tag_definitions(&self, owner: String) -> Result<Vec<TagDefinition>> {
+        Ok(self
+            .tag_definitions
+            .read()
+            .unwrap()
+            .get(&owner)
+            .map(|definitions| definitions.values().cloned().collect())
+            .unwrap_or_default())
+    }
+
+    fn store_tag_definition(&mut self, definition: TagDefinition) -> Result<()> {
+        self.tag_definitions
+            .write()
+            .unwrap()
+            .entry(definition.owner.clone())
+            .or_default()
+            .insert(definition.name.clone(), definition);
+        Ok(())
+    }
+
+    fn get_people(&self, owner: String) -> Result<Vec<Person>> {
+        let mut people: Vec<Person> = self
+            .people
+            .read()
+            .unwrap()
+            .values()
+            .filter(|person| person.owner == owner)
+            .cloned()
+            .collect();
+        people.sort_by(|person, other| {
+            person
+                .name
+                .cmp(&other.name)
+                .then_with(|| person.uuid.cmp(&other.uuid))
+        });
+        Ok(people)
+    }
+
+    fn get_person(&self, id: Uuid) -> Result<Person> {
+        self.people
+            .read()
+            .unwrap()
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| NotesError::NotFound(format!("No person found for id {}", id)))
+    }
+
+    fn store_person(&mut self, person: Person) -> Result<()> {
+        self.people.write().unwrap().insert(person.uuid, person);
+        Ok(())
+    }
 }
@@ -1,7 +1,16 @@
+#[cfg(any(test, feature = "conformance"))]
+pub mod conformance;
+mod file;
+mod index;
 mod memory;
 mod psql;
+#[cfg(feature = "sqlite")]
+mod sqlite;
 mod traits;
 
+pub use file::FileNoteStore;
 pub use memory::MemoryNoteStore;
 pub use psql::PsqlNoteStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteNoteStore;
 pub use traits::NoteStore;
@@ -1,126 +1,336 @@
-use crate::storage::psql::schema::notes;
-use crate::types::{ArchiveNoteRequest, Note, Tag, UpdateNoteRequest};
+use crate::storage::psql::schema::{
+    list_notes, lists, note_revisions, note_tags, notes, people, tag_definitions,
+};
+use crate::timestamp;
+use crate::types::{
+    ArchiveNoteRequest, List, Note, NoteRevision, Person, Tag, TagDefinition, UpdateNoteRequest,
+};
 use anyhow::{anyhow, Context};
+use chrono::{DateTime, Utc};
+use diesel::deserialize::{self, FromSql};
+use diesel::pg::Pg;
+use diesel::serialize::{self, Output, ToSql};
+use diesel::sql_types::{BigInt, Jsonb, Nullable, Varchar};
 use std::collections::HashSet;
-use std::convert::TryFrom;
+use std::io::Write;
 use uuid::Uuid;
 
-/// Our DB representaiton of a note
-#[derive(Queryable)]
+/// Our DB representaiton of a note. Its tags live in `note_tags`
+#[derive(Queryable, QueryableByName)]
+#[table_name = "notes"]
 pub struct DBNote {
     pub id: i32,
-    pub uuid: String,
+    pub uuid: Uuid,
     pub title: String,
     pub owner: String,
     pub description: String,
-    pub create_time: String,
-    pub last_update_time: String,
-    pub delete_time: Option<String>,
-    // TODO: impl Tag ToSql / FromSql
-    pub tags: Vec<String>,
-}
-
-// Conversions from type to DB type. I'm leaning towards TryFrom over from to allow for
-// errors, but maybe from would be better? Still gives flexibility?
-impl TryFrom<DBNote> for Note {
-    type Error = anyhow::Error;
-
-    fn try_from(note: DBNote) -> Result<Self, Self::Error> {
-        let mut tags = HashSet::new();
-        let uuid = Uuid::parse_str(&note.uuid).context("Parsing uuid")?;
-        for db_tag in note.tags {
-            let tag: Tag = serde_json::from_str(&db_tag).context("Deserializing tag")?;
-            if tags.contains(&tag) {
-                return Err(anyhow!("Tag {:?} already exists!", tag));
-            }
-            tags.insert(tag);
-        }
+    pub create_time: DateTime<Utc>,
+    pub last_update_time: DateTime<Utc>,
+    pub delete_time: Option<DateTime<Utc>>,
+    pub version: i64,
+    pub status: String,
+}
+
+impl DBNote {
+    /// Combine a note row with the rows of its tags
+    pub fn into_note(self, note_tags: Vec<DBNoteTag>) -> anyhow::Result<Note> {
+        let tags = note_tags
+            .into_iter()
+            .map(DBNoteTag::into_tag)
+            .collect::<anyhow::Result<HashSet<Tag>>>()?;
         Ok(Note {
-            uuid,
-            title: note.title,
-            description: note.description,
-            create_time: note.create_time,
-            last_update_time: note.last_update_time,
-            delete_time: note.delete_time,
-            tags: tags,
-            owner: note.owner,
+            uuid: self.uuid,
+            title: self.title,
+            description: self.description,
+            create_time: self.create_time,
+            last_update_time: self.last_update_time,
+            delete_time: self.delete_time,
+            tags,
+            owner: self.owner,
+            version: self.version as u64,
+            status: self.status.parse().context("Parsing status")?,
         })
     }
 }
 
+/// How many notes have a tag, see `PsqlNoteStore::tag_facets`
+#[derive(QueryableByName)]
+pub struct DBTagCount {
+    #[sql_type = "Varchar"]
+    pub kind: String,
+    /// None for the count of the whole variant
+    #[sql_type = "Nullable<Varchar>"]
+    pub value: Option<String>,
+    #[sql_type = "BigInt"]
+    pub count: i64,
+}
+
+/// Revisions keep their tags together, as JSONB, the way serde writes them, e.g. `"Book"` or
+/// `{"Origin": "A podcast"}`. Anything that isn't a tag fails to read
+impl ToSql<Jsonb, Pg> for Tag {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> serialize::Result {
+        let value = serde_json::to_value(self)?;
+        ToSql::<Jsonb, Pg>::to_sql(&value, out)
+    }
+}
+
+impl FromSql<Jsonb, Pg> for Tag {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let value = <serde_json::Value as FromSql<Jsonb, Pg>>::from_sql(bytes)?;
+        Ok(serde_json::from_value(value)?)
+    }
+}
+
 #[derive(Insertable)]
 #[table_name = "notes"]
 pub struct NewNote {
-    pub uuid: String,
+    pub uuid: Uuid,
     pub title: String,
     pub description: String,
-    pub create_time: String,
-    pub last_update_time: String,
-    pub delete_time: Option<String>,
+    pub create_time: DateTime<Utc>,
+    pub last_update_time: DateTime<Utc>,
+    pub delete_time: Option<DateTime<Utc>>,
     pub owner: String,
-    pub tags: Vec<String>,
+    pub version: i64,
+    pub status: String,
 }
 
-#[derive(AsChangeset, Default)]
+#[derive(AsChangeset)]
 #[table_name = "notes"]
 pub struct UpdateNote {
-    pub last_update_time: String,
+    pub last_update_time: DateTime<Utc>,
     pub title: Option<String>,
     pub description: Option<String>,
-    pub tags: Option<Vec<String>>,
-    pub delete_time: Option<String>,
-}
-
-impl TryFrom<UpdateNoteRequest> for UpdateNote {
-    type Error = anyhow::Error;
-    fn try_from(request: UpdateNoteRequest) -> Result<Self, Self::Error> {
-        let tag_update = match request.tags {
-            Some(tag_set) => {
-                let mut tags = Vec::new();
-                for tag in tag_set {
-                    tags.push(serde_json::to_string(&tag).context("serializing tag")?);
-                }
-                Some(tags)
-            }
-            None => None,
-        };
-        Ok(Self {
-            last_update_time: format!("{}", chrono::offset::Utc::now().timestamp()),
-            title: request.title,
-            description: request.description,
-            tags: tag_update,
+    pub status: Option<String>,
+    pub delete_time: Option<DateTime<Utc>>,
+}
+
+/// Tags are changed in `note_tags`, separately
+impl From<&UpdateNoteRequest> for UpdateNote {
+    fn from(request: &UpdateNoteRequest) -> Self {
+        Self {
+            last_update_time: timestamp::now(),
+            title: request.title.clone(),
+            description: request.description.clone(),
+            status: request.status.map(|status| status.as_str().to_string()),
             delete_time: None,
-        })
+        }
     }
 }
 
 impl From<ArchiveNoteRequest> for UpdateNote {
     fn from(_request: ArchiveNoteRequest) -> Self {
-        let now = format!("{}", chrono::offset::Utc::now().timestamp());
+        let now = timestamp::now();
         Self {
-            last_update_time: now.clone(),
+            last_update_time: now,
+            title: None,
+            description: None,
+            status: None,
             delete_time: Some(now),
-            ..Self::default()
         }
     }
 }
 
-impl TryFrom<Note> for NewNote {
-    type Error = anyhow::Error;
-    fn try_from(note: Note) -> Result<Self, Self::Error> {
-        let mut tags = Vec::new();
-        for tag in note.tags {
-            tags.push(serde_json::to_string(&tag).context("serializing tag")?);
-        }
-        Ok(NewNote {
-            uuid: note.uuid.to_string(),
-            title: note.title,
-            description: note.description,
+impl From<&Note> for NewNote {
+    fn from(note: &Note) -> Self {
+        NewNote {
+            uuid: note.uuid,
+            title: note.title.clone(),
+            description: note.description.clone(),
             create_time: note.create_time,
             last_update_time: note.last_update_time,
             delete_time: note.delete_time,
-            owner: note.owner,
-            tags,
+            owner: note.owner.clone(),
+            version: note.version as i64,
+            status: note.status.as_str().to_string(),
+        }
+    }
+}
+
+/// A single tag of a note, split into its variant and payload so that either can be
+/// looked up by index
+#[derive(Queryable, Insertable)]
+#[table_name = "note_tags"]
+pub struct DBNoteTag {
+    pub note_id: i32,
+    pub kind: String,
+    /// None for tags without a payload, like `Book`
+    pub value: Option<String>,
+}
+
+impl DBNoteTag {
+    pub fn for_tags(note_id: i32, tags: &HashSet<Tag>) -> Vec<DBNoteTag> {
+        tags.iter()
+            .map(|tag| DBNoteTag {
+                note_id,
+                kind: tag.kind().to_string(),
+                value: tag.payload().map(str::to_string),
+            })
+            .collect()
+    }
+
+    pub fn into_tag(self) -> anyhow::Result<Tag> {
+        Tag::from_parts(&self.kind, self.value.as_deref())
+            .ok_or_else(|| anyhow!("{}({:?}) is not a tag", self.kind, self.value))
+    }
+}
+
+/// Our DB representation of a list. The notes in the list live in `list_notes`
+#[derive(Queryable)]
+pub struct DBList {
+    pub id: i32,
+    pub uuid: String,
+    pub title: String,
+    pub owner: String,
+    pub description: String,
+}
+
+impl DBList {
+    /// Combine a list row with its (already position ordered) note entries
+    pub fn into_list(self, list_notes: Vec<DBListNote>) -> anyhow::Result<List> {
+        let uuid = Uuid::parse_str(&self.uuid).context("Parsing list uuid")?;
+        let notes = list_notes
+            .into_iter()
+            .map(|list_note| Uuid::parse_str(&list_note.note_uuid).context("Parsing note uuid"))
+            .collect::<anyhow::Result<Vec<Uuid>>>()?;
+        Ok(List {
+            uuid,
+            notes,
+            title: self.title,
+            owner: self.owner,
+            description: self.description,
         })
     }
 }
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "lists"]
+pub struct NewList {
+    pub uuid: String,
+    pub title: String,
+    pub owner: String,
+    pub description: String,
+}
+
+impl From<&List> for NewList {
+    fn from(list: &List) -> Self {
+        NewList {
+            uuid: list.uuid.to_string(),
+            title: list.title.clone(),
+            owner: list.owner.clone(),
+            description: list.description.clone(),
+        }
+    }
+}
+
+/// A single entry of a list, keyed by its position in that list
+#[derive(Queryable, Insertable)]
+#[table_name = "list_notes"]
+pub struct DBListNote {
+    pub list_id: i32,
+    pub position: i32,
+    pub note_uuid: String,
+}
+
+/// A past version of a note
+#[derive(Queryable, Insertable)]
+#[table_name = "note_revisions"]
+pub struct DBNoteRevision {
+    pub note_id: i32,
+    pub revision: i32,
+    pub title: String,
+    pub description: String,
+    pub tags: Vec<Tag>,
+    pub last_update_time: DateTime<Utc>,
+}
+
+impl DBNoteRevision {
+    pub fn new(note_id: i32, revision: &NoteRevision) -> DBNoteRevision {
+        DBNoteRevision {
+            note_id,
+            revision: revision.revision as i32,
+            title: revision.title.clone(),
+            description: revision.description.clone(),
+            tags: revision.tags.iter().cloned().collect(),
+            last_update_time: revision.last_update_time,
+        }
+    }
+
+    /// Revision rows only know the id of their note, so its uuid has to be given
+    pub fn into_revision(self, note_id: Uuid) -> NoteRevision {
+        NoteRevision {
+            note_id,
+            revision: self.revision as u32,
+            title: self.title,
+            description: self.description,
+            tags: self.tags.into_iter().collect(),
+            last_update_time: self.last_update_time,
+        }
+    }
+}
+
+/// A custom tag, stored as it was defined
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "tag_definitions"]
+pub struct DBTagDefinition {
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub colour: String,
+}
+
+impl From<TagDefinition> for DBTagDefinition {
+    fn from(definition: TagDefinition) -> Self {
+        DBTagDefinition {
+            owner: definition.owner,
+            name: definition.name,
+            description: definition.description,
+            colour: definition.colour,
+        }
+    }
+}
+
+impl From<DBTagDefinition> for TagDefinition {
+    fn from(definition: DBTagDefinition) -> Self {
+        TagDefinition {
+            owner: definition.owner,
+            name: definition.name,
+            description: definition.description,
+            colour: definition.colour,
+        }
+    }
+}
+
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "people"]
+pub struct DBPerson {
+    pub uuid: Uuid,
+    pub owner: String,
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub description: String,
+}
+
+impl From<Person> for DBPerson {
+    fn from(person: Person) -> Self {
+        DBPerson {
+            uuid: person.uuid,
+            owner: person.owner,
+            name: person.name,
+            aliases: person.aliases,
+            description: person.description,
+        }
+    }
+}
+
+impl From<DBPerson> for Person {
+    fn from(person: DBPerson) -> Self {
+        Person {
+            uuid: person.uuid,
+            owner: person.owner,
+            name: person.name,
+            aliases: person.aliases,
+            description: person.description,
+        }
+    }
+}
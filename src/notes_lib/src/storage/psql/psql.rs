@@ -1,14 +1,28 @@
-use super::models::{DBNote, NewNote, UpdateNote};
-use super::schema::notes;
+use super::models::{
+    DBList, DBListNote, DBNote, DBNoteRevision, DBNoteTag, DBPerson, DBTagCount, DBTagDefinition,
+    NewList, NewNote, UpdateNote,
+};
+use super::schema::{list_notes, lists, note_revisions, note_tags, notes, people, tag_definitions};
+use crate::error::{NotesError, Result};
 use crate::storage::traits::NoteStore;
-use crate::types::{ArchiveNoteRequest, FullList, List, Note, UpdateNoteRequest};
-use anyhow::{anyhow, Context, Result};
-use diesel::pg::PgConnection;
+use crate::timestamp;
+use crate::types::{
+    ArchiveNoteRequest, ArchivedNotes, FullList, List, Note, NoteRevision, NoteSort, NotesPage,
+    NotesQuery, Person, Tag, TagDefinition, TagFacet, TagQuery, TagValueCount,
+    UnarchiveNoteRequest, UpdateNoteRequest,
+};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use diesel::dsl::{not, sql};
+use diesel::expression::BoxableExpression;
+use diesel::pg::{Pg, PgConnection};
 use diesel::r2d2::{ConnectionManager, Pool, PoolError, PooledConnection};
-use diesel::{ExpressionMethods, QueryDsl, RunQueryDsl};
+use diesel::sql_types::{Bool, Text, Timestamptz, Uuid as SqlUuid};
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
 use log::info;
-use std::collections::HashMap;
-use std::convert::TryFrom;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 pub type DBPool = Pool<ConnectionManager<PgConnection>>;
@@ -19,6 +33,37 @@ pub fn create_pool(psql_str: &str) -> std::result::Result<DBPool, PoolError> {
     Pool::builder().build(manager)
 }
 
+type NotesFilter = Box<dyn BoxableExpression<notes::table, Pg, SqlType = Bool>>;
+
+/// Translate a tag query into a condition on the `note_tags` of a note. Each tag is
+/// looked up through the note_tags_kind_value index
+fn tag_filter(query: &TagQuery) -> NotesFilter {
+    let has_tag = "EXISTS (SELECT 1 FROM note_tags \
+                   WHERE note_tags.note_id = notes.id AND note_tags.kind = ";
+    match query {
+        TagQuery::Tag(tag) => {
+            let has_kind = sql::<Bool>(has_tag).bind::<Text, _>(tag.kind());
+            match tag.payload() {
+                Some(payload) => Box::new(
+                    has_kind
+                        .sql(" AND note_tags.value = ")
+                        .bind::<Text, _>(payload.to_string())
+                        .sql(")"),
+                ),
+                None => Box::new(has_kind.sql(" AND note_tags.value IS NULL)")),
+            }
+        }
+        TagQuery::AnyPayload(kind) => Box::new(
+            sql::<Bool>(has_tag)
+                .bind::<Text, _>(kind.clone())
+                .sql(" AND note_tags.value IS NOT NULL)"),
+        ),
+        TagQuery::And(left, right) => Box::new(tag_filter(left).and(tag_filter(right))),
+        TagQuery::Or(left, right) => Box::new(tag_filter(left).or(tag_filter(right))),
+        TagQuery::Not(query) => Box::new(not(tag_filter(query))),
+    }
+}
+
 #[derive(Clone)]
 pub struct PsqlNoteStore {
     db_pool: DBPool,
@@ -39,87 +84,596 @@ impl NoteStore for PsqlNoteStore {
     fn get_note(&self, id: Uuid) -> Result<Note> {
         let conn = self.get_db_conn()?;
         info!("Looking for note {}", id);
-        let mut db_notes = notes::dsl::notes
-            .filter(notes::dsl::uuid.eq(id.to_string()))
-            .load::<DBNote>(&conn)
-            .context(format!("Looking for note with id {}", id))?;
-
-        if db_notes.len() > 1 {
-            return Err(anyhow!("Multiple notes found for uuid {}", id));
-        }
-        match db_notes.pop() {
-            Some(db_note) => Note::try_from(db_note).context("reading db note"),
-            None => Err(anyhow!("No note found for id {}", id)),
-        }
+        let db_note = notes::dsl::notes
+            .filter(notes::dsl::uuid.eq(id))
+            .first::<DBNote>(&conn)
+            .optional()
+            .context(format!("Looking for note with id {}", id))?
+            .ok_or_else(|| NotesError::NotFound(format!("No note found for id {}", id)))?;
+        Ok(load_tags(&conn, vec![db_note])?.remove(0))
     }
 
-    fn get_notes(&self, owner: String) -> Result<HashMap<Uuid, Note>> {
+    fn get_notes(&self, owner: String, query: NotesQuery) -> Result<NotesPage> {
         let conn = self.get_db_conn()?;
-        let db_notes = notes::dsl::notes
+        // Compare titles bytewise like the other backends, which is also how the
+        // notes_owner_title index sorts
+        let key = match query.sort {
+            NoteSort::Title => "title COLLATE \"C\"",
+            sort => sort.column(),
+        };
+        let mut db_query = notes::dsl::notes
             .filter(notes::dsl::owner.eq(&owner))
+            .order(sql::<Text>(&format!("{}, uuid", key)))
+            .into_boxed();
+        db_query = match query.archived {
+            ArchivedNotes::Exclude => db_query.filter(notes::dsl::delete_time.is_null()),
+            ArchivedNotes::Include => db_query,
+            ArchivedNotes::Only => db_query.filter(notes::dsl::delete_time.is_not_null()),
+        };
+        if let Some(status) = query.status {
+            db_query = db_query.filter(notes::dsl::status.eq(status.as_str()));
+        }
+        if let Some(tags) = &query.tags {
+            db_query = db_query.filter(tag_filter(tags));
+        }
+        if let Some(since) = query.since {
+            db_query = db_query.filter(notes::dsl::create_time.ge(since));
+        }
+        if let Some(until) = query.until {
+            db_query = db_query.filter(notes::dsl::create_time.lt(until));
+        }
+        if let Some(after) = &query.after {
+            let after_key = sql::<Bool>(&format!("({}, uuid) > (", key));
+            db_query = match after.time()? {
+                Some(time) => db_query.filter(
+                    after_key
+                        .bind::<Timestamptz, _>(time)
+                        .sql(", ")
+                        .bind::<SqlUuid, _>(after.uuid)
+                        .sql(")"),
+                ),
+                None => db_query.filter(
+                    after_key
+                        .bind::<Text, _>(&after.key)
+                        .sql(", ")
+                        .bind::<SqlUuid, _>(after.uuid)
+                        .sql(")"),
+                ),
+            };
+        }
+        if let Some(limit) = query.limit {
+            // One more than asked for tells NotesPage whether there is a next page
+            db_query = db_query.limit(limit as i64 + 1);
+        }
+        let db_notes = db_query
             .load::<DBNote>(&conn)
             .context(format!("Looking for owner {}", owner))?;
-        let mut resulting_map = HashMap::new();
-        let notes: Vec<Note> = db_notes
-            .into_iter()
-            .map(|db_note| Note::try_from(db_note))
-            .collect::<Result<Vec<Note>>>()
-            .context(format!("Reading notes for {}", owner))?;
-        // TODO Stop the attack of the clones
-        for note in notes {
-            resulting_map.insert(note.uuid.clone(), note.clone());
+        let notes = load_tags(&conn, db_notes).context(format!("Reading notes for {}", owner))?;
+        Ok(NotesPage::new(notes, query.sort, query.limit))
+    }
+
+    fn search_notes(&self, owner: String, query: String) -> Result<Vec<Note>> {
+        let conn = self.get_db_conn()?;
+        // plainto_tsquery matches every word of the query, like the other backends.
+        // Ties are broken the same way as the memory store, by title and then uuid.
+        let db_notes = diesel::sql_query(
+            "SELECT id, uuid, title, owner, description, create_time, last_update_time, \
+                    delete_time, version, status \
+             FROM notes \
+             WHERE owner = $1 AND search @@ plainto_tsquery('english', $2) \
+             ORDER BY ts_rank(search, plainto_tsquery('english', $2)) DESC, \
+                      title COLLATE \"C\", uuid",
+        )
+        .bind::<Text, _>(&owner)
+        .bind::<Text, _>(&query)
+        .load::<DBNote>(&conn)
+        .context(format!("Searching notes of {} for {}", owner, query))?;
+        Ok(load_tags(&conn, db_notes).context(format!("Reading notes for {}", owner))?)
+    }
+
+    fn tag_facets(&self, owner: String) -> Result<Vec<TagFacet>> {
+        let conn = self.get_db_conn()?;
+        // Counted per variant and per payload at once. Rows of the whole variant have no value,
+        // and the per payload rows of variants without a payload are left out
+        let counts = diesel::sql_query(
+            "SELECT kind, value, COUNT(DISTINCT note_id) AS count \
+             FROM note_tags JOIN notes ON notes.id = note_tags.note_id \
+             WHERE owner = $1 AND delete_time IS NULL \
+             GROUP BY GROUPING SETS ((kind), (kind, value)) \
+             HAVING GROUPING(value) = 1 OR value IS NOT NULL",
+        )
+        .bind::<Text, _>(&owner)
+        .load::<DBTagCount>(&conn)
+        .context(format!("Counting tags of {}", owner))?;
+        let mut facets: HashMap<String, TagFacet> = HashMap::new();
+        for count in counts {
+            let facet = facets
+                .entry(count.kind.clone())
+                .or_insert_with(|| TagFacet {
+                    kind: count.kind,
+                    count: 0,
+                    values: Vec::new(),
+                });
+            match count.value {
+                Some(value) => facet.values.push(TagValueCount {
+                    value,
+                    count: count.count as u64,
+                }),
+                None => facet.count = count.count as u64,
+            }
         }
-        Ok(resulting_map)
+        Ok(TagFacet::sorted(facets.into_values().collect()))
     }
 
     fn create_note(&mut self, note: Note) -> Result<()> {
         let conn = self.get_db_conn()?;
-        let note_uuid = note.uuid.clone();
-        let new_note_request = NewNote::try_from(note).context(format!(
-            "attempting to create insert statement for note with uuid {}",
-            note_uuid
-        ))?;
-        diesel::insert_into(notes::table)
-            .values(&new_note_request)
-            .execute(&conn)
-            .context(format!(
-                "Error attempting to persist note in db with uuid {}",
-                note_uuid
-            ))?;
+        conn.transaction::<_, anyhow::Error, _>(|| {
+            let note_id = diesel::insert_into(notes::table)
+                .values(&NewNote::from(&note))
+                .returning(notes::dsl::id)
+                .get_result::<i32>(&conn)
+                .context(format!(
+                    "Error attempting to persist note in db with uuid {}",
+                    note.uuid
+                ))?;
+            insert_tags(&conn, note_id, &note.tags)
+                .context(format!("Storing tags for note with uuid {}", note.uuid))?;
+            Ok(())
+        })?;
         Ok(())
     }
 
     fn update_note(&mut self, request: UpdateNoteRequest) -> Result<()> {
-        let note_id = request.note_id.clone();
-        let update = UpdateNote::try_from(request).context("converting update request")?;
         let conn = self.get_db_conn()?;
-        diesel::update(notes::dsl::notes.filter(notes::dsl::uuid.eq(note_id.to_string())))
-            .set(&update)
-            .execute(&conn)
-            .context("Updating note")?;
-        Ok(())
+        conn.transaction(|| update_note_in(&conn, &request))?
+    }
+
+    fn rename_tag(
+        &mut self,
+        owner: String,
+        from: Tag,
+        to: Tag,
+        dry_run: bool,
+    ) -> Result<Vec<Note>> {
+        let conn = self.get_db_conn()?;
+        let notes = conn.transaction::<_, anyhow::Error, _>(|| {
+            // Lock the notes, so every note is retagged at once
+            let db_notes = notes::dsl::notes
+                .filter(notes::dsl::owner.eq(&owner))
+                .filter(tag_filter(&TagQuery::Tag(from.clone())))
+                .for_update()
+                .load::<DBNote>(&conn)
+                .context(format!("Looking for notes of {} tagged {:?}", owner, from))?;
+            let notes = load_tags(&conn, db_notes).context("Reading notes to retag")?;
+            if dry_run {
+                return Ok(notes);
+            }
+            for note in &notes {
+                let mut tags = note.tags.clone();
+                tags.remove(&from);
+                tags.insert(to.clone());
+                let request = UpdateNoteRequest {
+                    note_id: note.uuid,
+                    tags: Some(tags),
+                    ..UpdateNoteRequest::default()
+                };
+                // Fail the transaction itself, so notes already retagged are rolled back
+                update_note_in(&conn, &request)?.map_err(anyhow::Error::from)?;
+            }
+            Ok(notes)
+        })?;
+        Ok(notes)
+    }
+
+    fn get_note_history(&self, id: Uuid) -> Result<Vec<NoteRevision>> {
+        let conn = self.get_db_conn()?;
+        let db_id = notes::dsl::notes
+            .filter(notes::dsl::uuid.eq(id))
+            .select(notes::dsl::id)
+            .first::<i32>(&conn)
+            .optional()
+            .context(format!("Looking for note with id {}", id))?
+            .ok_or_else(|| NotesError::NotFound(format!("No note found for id {}", id)))?;
+        let revisions = note_revisions::dsl::note_revisions
+            .filter(note_revisions::dsl::note_id.eq(db_id))
+            .order(note_revisions::dsl::revision)
+            .load::<DBNoteRevision>(&conn)
+            .context(format!("Loading history of note {}", id))?;
+        Ok(revisions
+            .into_iter()
+            .map(|revision| revision.into_revision(id))
+            .collect())
     }
 
     fn archive_note(&mut self, request: ArchiveNoteRequest) -> Result<()> {
         let note_id = request.note_id.clone();
         let update = UpdateNote::from(request);
         let conn = self.get_db_conn()?;
-        diesel::update(notes::dsl::notes.filter(notes::dsl::uuid.eq(note_id.to_string())))
-            .set(&update)
+        let updated = diesel::update(notes::dsl::notes.filter(notes::dsl::uuid.eq(note_id)))
+            .set((&update, notes::dsl::version.eq(notes::dsl::version + 1)))
             .execute(&conn)
             .context("Updating note")?;
+        if updated == 0 {
+            return Err(NotesError::NotFound(format!(
+                "No note found for id {}",
+                note_id
+            )));
+        }
         Ok(())
     }
 
+    fn unarchive_note(&mut self, request: UnarchiveNoteRequest) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let now = timestamp::now();
+        let updated =
+            diesel::update(notes::dsl::notes.filter(notes::dsl::uuid.eq(request.note_id)))
+                .set((
+                    notes::dsl::last_update_time.eq(now),
+                    notes::dsl::delete_time.eq(None::<DateTime<Utc>>),
+                    notes::dsl::version.eq(notes::dsl::version + 1),
+                ))
+                .execute(&conn)
+                .context("Unarchiving note")?;
+        if updated == 0 {
+            return Err(NotesError::NotFound(format!(
+                "No note found for id {}",
+                request.note_id
+            )));
+        }
+        Ok(())
+    }
+
+    fn purge_note(&mut self, id: Uuid) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let deleted = conn.transaction::<_, anyhow::Error, _>(|| {
+            remove_from_lists(&conn, id)?;
+            let deleted = diesel::delete(notes::dsl::notes.filter(notes::dsl::uuid.eq(id)))
+                .execute(&conn)
+                .context(format!("Deleting note with id {}", id))?;
+            Ok(deleted)
+        })?;
+        if deleted == 0 {
+            return Err(NotesError::NotFound(format!("No note found for id {}", id)));
+        }
+        Ok(())
+    }
+
+    fn purge_archived_before(&mut self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<Vec<Note>> {
+        let conn = self.get_db_conn()?;
+        let notes = conn.transaction::<_, anyhow::Error, _>(|| {
+            // Lock the notes, so nothing is unarchived while we purge it
+            let db_notes = notes::dsl::notes
+                .filter(notes::dsl::delete_time.lt(cutoff))
+                .for_update()
+                .load::<DBNote>(&conn)
+                .context("Looking for expired notes")?;
+            let notes = load_tags(&conn, db_notes).context("Reading expired notes")?;
+            if dry_run {
+                return Ok(notes);
+            }
+            for note in &notes {
+                remove_from_lists(&conn, note.uuid)?;
+            }
+            let ids: Vec<Uuid> = notes.iter().map(|note| note.uuid).collect();
+            diesel::delete(notes::dsl::notes.filter(notes::dsl::uuid.eq_any(&ids)))
+                .execute(&conn)
+                .context("Deleting expired notes")?;
+            Ok(notes)
+        })?;
+        Ok(notes)
+    }
+
+    fn get_list(&self, id: Uuid) -> Result<List> {
+        let conn = self.get_db_conn()?;
+        load_list(&conn, id)
+    }
+
     fn get_full_list(&self, id: Uuid) -> Result<FullList> {
-        todo!()
+        let conn = self.get_db_conn()?;
+        let list = load_list(&conn, id)?;
+        let db_notes = notes::dsl::notes
+            .filter(notes::dsl::uuid.eq_any(&list.notes))
+            .load::<DBNote>(&conn)
+            .context(format!("Looking for notes in list {}", id))?;
+        let notes_in_list: HashMap<Uuid, Note> = load_tags(&conn, db_notes)?
+            .into_iter()
+            .map(|note| (note.uuid, note))
+            .collect();
+        // TODO --> What happens if I have a deleted note in a list?
+        for note_id in &list.notes {
+            if !notes_in_list.contains_key(note_id) {
+                return Err(NotesError::NotFound(format!(
+                    "No note found for id {} in list {}",
+                    note_id, id
+                )));
+            }
+        }
+        Ok(FullList {
+            list,
+            notes_in_list,
+        })
     }
 
     fn get_lists(&self, owner: String) -> Result<HashMap<Uuid, List>> {
-        todo!()
+        let conn = self.get_db_conn()?;
+        let db_lists = lists::dsl::lists
+            .filter(lists::dsl::owner.eq(&owner))
+            .load::<DBList>(&conn)
+            .context(format!("Looking for lists of owner {}", owner))?;
+        let list_ids: Vec<i32> = db_lists.iter().map(|db_list| db_list.id).collect();
+        let mut db_list_notes: HashMap<i32, Vec<DBListNote>> = HashMap::new();
+        for list_note in list_notes::dsl::list_notes
+            .filter(list_notes::dsl::list_id.eq_any(&list_ids))
+            .order(list_notes::dsl::position)
+            .load::<DBListNote>(&conn)
+            .context(format!("Looking for notes in lists of owner {}", owner))?
+        {
+            db_list_notes
+                .entry(list_note.list_id)
+                .or_default()
+                .push(list_note);
+        }
+
+        let mut resulting_map = HashMap::new();
+        for db_list in db_lists {
+            let entries = db_list_notes.remove(&db_list.id).unwrap_or_default();
+            let list = db_list
+                .into_list(entries)
+                .context(format!("Reading lists for {}", owner))?;
+            resulting_map.insert(list.uuid, list);
+        }
+        Ok(resulting_map)
     }
 
     fn store_list(&mut self, list: List) -> Result<()> {
-        todo!()
+        let conn = self.get_db_conn()?;
+        let new_list = NewList::from(&list);
+        conn.transaction::<_, anyhow::Error, _>(|| {
+            // Lists are stored whole, so upsert the list itself and replace its entries
+            let list_id = diesel::insert_into(lists::table)
+                .values(&new_list)
+                .on_conflict(lists::dsl::uuid)
+                .do_update()
+                .set(&new_list)
+                .returning(lists::dsl::id)
+                .get_result::<i32>(&conn)
+                .context(format!("Storing list with uuid {}", list.uuid))?;
+            diesel::delete(
+                list_notes::dsl::list_notes.filter(list_notes::dsl::list_id.eq(list_id)),
+            )
+            .execute(&conn)
+            .context(format!("Clearing notes of list with uuid {}", list.uuid))?;
+            let entries: Vec<DBListNote> = list
+                .notes
+                .iter()
+                .enumerate()
+                .map(|(position, note_id)| DBListNote {
+                    list_id,
+                    position: position as i32,
+                    note_uuid: note_id.to_string(),
+                })
+                .collect();
+            diesel::insert_into(list_notes::table)
+                .values(&entries)
+                .execute(&conn)
+                .context(format!("Storing notes of list with uuid {}", list.uuid))?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn delete_list(&mut self, id: Uuid) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        // The list's entries in list_notes are removed by the ON DELETE CASCADE
+        let deleted = diesel::delete(lists::dsl::lists.filter(lists::dsl::uuid.eq(id.to_string())))
+            .execute(&conn)
+            .context(format!("Deleting list with id {}", id))?;
+        if deleted == 0 {
+            return Err(NotesError::NotFound(format!("No list found for id {}", id)));
+        }
+        Ok(())
+    }
+
+    fn get_tag_definitions(&self, owner: String) -> Result<Vec<TagDefinition>> {
+        let conn = self.get_db_conn()?;
+        let definitions = tag_definitions::dsl::tag_definitions
+            .filter(tag_definitions::dsl::owner.eq(&owner))
+            .order(tag_definitions::dsl::name)
+            .load::<DBTagDefinition>(&conn)
+            .context(format!("Looking for tags defined by {}", owner))?;
+        Ok(definitions.into_iter().map(TagDefinition::from).collect())
+    }
+
+    fn store_tag_definition(&mut self, definition: TagDefinition) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let name = definition.name.clone();
+        let definition = DBTagDefinition::from(definition);
+        diesel::insert_into(tag_definitions::table)
+            .values(&definition)
+            .on_conflict((tag_definitions::dsl::owner, tag_definitions::dsl::name))
+            .do_update()
+            .set(&definition)
+            .execute(&conn)
+            .context(format!("Storing tag {}", name))?;
+        Ok(())
+    }
+
+    fn get_people(&self, owner: String) -> Result<Vec<Person>> {
+        let conn = self.get_db_conn()?;
+        let people = people::dsl::people
+            .filter(people::dsl::owner.eq(&owner))
+            .order((people::dsl::name, people::dsl::uuid))
+            .load::<DBPerson>(&conn)
+            .context(format!("Looking for people of {}", owner))?;
+        Ok(people.into_iter().map(Person::from).collect())
+    }
+
+    fn get_person(&self, id: Uuid) -> Result<Person> {
+        let conn = self.get_db_conn()?;
+        let person = people::dsl::people
+            .find(id)
+            .first::<DBPerson>(&conn)
+            .optional()
+            .context(format!("Looking for person with id {}", id))?
+            .ok_or_else(|| NotesError::NotFound(format!("No person found for id {}", id)))?;
+        Ok(Person::from(person))
     }
+
+    fn store_person(&mut self, person: Person) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let id = person.uuid;
+        let person = DBPerson::from(person);
+        diesel::insert_into(people::table)
+            .values(&person)
+            .on_conflict(people::dsl::uuid)
+            .do_update()
+            .set(&person)
+            .execute(&conn)
+            .context(format!("Storing person {}", id))?;
+        Ok(())
+    }
+}
+
+/// Update a note within an open transaction. A note that is missing or has moved on
+/// is an answer rather than a failure, so it comes back as the inner Result
+fn update_note_in(conn: &DBCon, request: &UpdateNoteRequest) -> anyhow::Result<Result<()>> {
+    let note_id = request.note_id;
+    let update = UpdateNote::from(request);
+    // Lock the note, so two updates can't both record the same revision
+    let current = notes::dsl::notes
+        .filter(notes::dsl::uuid.eq(note_id))
+        .for_update()
+        .first::<DBNote>(conn)
+        .optional()
+        .context(format!("Looking for note with id {}", note_id))?;
+    let db_note = match current {
+        Some(db_note) => db_note,
+        None => {
+            return Ok(Err(NotesError::NotFound(format!(
+                "No note found for id {}",
+                note_id
+            ))))
+        }
+    };
+    let db_id = db_note.id;
+    let current = load_tags(conn, vec![db_note])?.remove(0);
+    let expected = request.expected_version.unwrap_or(current.version);
+    let updated = diesel::update(
+        notes::dsl::notes
+            .find(db_id)
+            .filter(notes::dsl::version.eq(expected as i64)),
+    )
+    .set((&update, notes::dsl::version.eq(notes::dsl::version + 1)))
+    .execute(conn)
+    .context("Updating note")?;
+    if updated == 0 {
+        return Ok(Err(NotesError::Conflict(format!(
+            "Note {} is at version {}, not {}",
+            note_id, current.version, expected
+        ))));
+    }
+    let latest = note_revisions::dsl::note_revisions
+        .filter(note_revisions::dsl::note_id.eq(db_id))
+        .select(note_revisions::dsl::revision)
+        .order(note_revisions::dsl::revision.desc())
+        .first::<i32>(conn)
+        .optional()
+        .context("Looking for the latest revision")?;
+    let revision = NoteRevision::of(&current, latest.unwrap_or(0) as u32 + 1);
+    diesel::insert_into(note_revisions::table)
+        .values(&DBNoteRevision::new(db_id, &revision))
+        .execute(conn)
+        .context("Recording revision")?;
+    if let Some(tags) = &request.tags {
+        diesel::delete(note_tags::dsl::note_tags.filter(note_tags::dsl::note_id.eq(db_id)))
+            .execute(conn)
+            .context("Clearing tags of note")?;
+        insert_tags(conn, db_id, tags).context("Storing tags of note")?;
+    }
+    Ok(Ok(()))
+}
+
+fn insert_tags(conn: &DBCon, note_id: i32, tags: &HashSet<Tag>) -> anyhow::Result<()> {
+    diesel::insert_into(note_tags::table)
+        .values(&DBNoteTag::for_tags(note_id, tags))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Fill in the tags of each note, keeping the order of `db_notes`
+fn load_tags(conn: &DBCon, db_notes: Vec<DBNote>) -> anyhow::Result<Vec<Note>> {
+    let note_ids: Vec<i32> = db_notes.iter().map(|db_note| db_note.id).collect();
+    let mut db_note_tags: HashMap<i32, Vec<DBNoteTag>> = HashMap::new();
+    for note_tag in note_tags::dsl::note_tags
+        .filter(note_tags::dsl::note_id.eq_any(&note_ids))
+        .load::<DBNoteTag>(conn)
+        .context("Looking for tags of notes")?
+    {
+        db_note_tags
+            .entry(note_tag.note_id)
+            .or_default()
+            .push(note_tag);
+    }
+    db_notes
+        .into_iter()
+        .map(|db_note| {
+            let tags = db_note_tags.remove(&db_note.id).unwrap_or_default();
+            db_note.into_note(tags).context("reading db note")
+        })
+        .collect()
+}
+
+/// Take a note out of every list it is in, closing the gaps it leaves behind
+fn remove_from_lists(conn: &DBCon, id: Uuid) -> anyhow::Result<()> {
+    let list_ids: Vec<i32> = list_notes::dsl::list_notes
+        .filter(list_notes::dsl::note_uuid.eq(id.to_string()))
+        .select(list_notes::dsl::list_id)
+        .distinct()
+        .load(conn)
+        .context(format!("Looking for lists with note {}", id))?;
+    if list_ids.is_empty() {
+        return Ok(());
+    }
+    let kept = list_notes::dsl::list_notes
+        .filter(list_notes::dsl::list_id.eq_any(&list_ids))
+        .filter(list_notes::dsl::note_uuid.ne(id.to_string()))
+        .order((list_notes::dsl::list_id, list_notes::dsl::position))
+        .load::<DBListNote>(conn)
+        .context("Looking for the rest of the lists")?;
+    diesel::delete(list_notes::dsl::list_notes.filter(list_notes::dsl::list_id.eq_any(&list_ids)))
+        .execute(conn)
+        .context("Clearing lists")?;
+    let mut next_positions: HashMap<i32, i32> = HashMap::new();
+    let entries: Vec<DBListNote> = kept
+        .into_iter()
+        .map(|entry| {
+            let next_position = next_positions.entry(entry.list_id).or_default();
+            let position = *next_position;
+            *next_position += 1;
+            DBListNote { position, ..entry }
+        })
+        .collect();
+    diesel::insert_into(list_notes::table)
+        .values(&entries)
+        .execute(conn)
+        .context("Storing the rest of the lists")?;
+    Ok(())
+}
+
+/// Load a list and its ordered note ids
+fn load_list(conn: &DBCon, id: Uuid) -> Result<List> {
+    let db_list = lists::dsl::lists
+        .filter(lists::dsl::uuid.eq(id.to_string()))
+        .first::<DBList>(conn)
+        .optional()
+        .context(format!("Looking for list with id {}", id))?
+        .ok_or_else(|| NotesError::NotFound(format!("No list found for id {}", id)))?;
+    let db_list_notes = list_notes::dsl::list_notes
+        .filter(list_notes::dsl::list_id.eq(db_list.id))
+        .order(list_notes::dsl::position)
+        .load::<DBListNote>(conn)
+        .context(format!("Looking for notes in list {}", id))?;
+    Ok(db_list
+        .into_list(db_list_notes)
+        .context("reading db list")?)
 }
@@ -1,13 +1,84 @@
 table! {
-    notes (id) {
+    list_notes (list_id, position) {
+        list_id -> Int4,
+        position -> Int4,
+        note_uuid -> Varchar,
+    }
+}
+
+table! {
+    lists (id) {
         id -> Int4,
         uuid -> Varchar,
         title -> Varchar,
         owner -> Varchar,
         description -> Text,
-        create_time -> Varchar,
-        last_update_time -> Varchar,
-        delete_time -> Nullable<Varchar>,
-        tags -> Array<Text>,
     }
 }
+
+table! {
+    note_tags (note_id, kind, value) {
+        note_id -> Int4,
+        kind -> Varchar,
+        value -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    note_revisions (note_id, revision) {
+        note_id -> Int4,
+        revision -> Int4,
+        title -> Varchar,
+        description -> Text,
+        tags -> Array<Jsonb>,
+        last_update_time -> Timestamptz,
+    }
+}
+
+table! {
+    notes (id) {
+        id -> Int4,
+        uuid -> Uuid,
+        title -> Varchar,
+        owner -> Varchar,
+        description -> Text,
+        create_time -> Timestamptz,
+        last_update_time -> Timestamptz,
+        delete_time -> Nullable<Timestamptz>,
+        version -> Int8,
+        status -> Varchar,
+    }
+}
+
+table! {
+    people (uuid) {
+        uuid -> Uuid,
+        owner -> Varchar,
+        name -> Varchar,
+        aliases -> Array<Varchar>,
+        description -> Text,
+    }
+}
+
+table! {
+    tag_definitions (owner, name) {
+        owner -> Varchar,
+        name -> Varchar,
+        description -> Text,
+        colour -> Varchar,
+    }
+}
+
+joinable!(list_notes -> lists (list_id));
+joinable!(note_revisions -> notes (note_id));
+joinable!(note_tags -> notes (note_id));
+
+allow_tables_to_appear_in_same_query!(
+    list_notes,
+    lists,
+    note_revisions,
+    note_tags,
+    notes,
+    people,
+    tag_definitions,
+);
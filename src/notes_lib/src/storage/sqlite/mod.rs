@@ -0,0 +1,5 @@
+mod models;
+mod schema;
+mod sqlite;
+
+pub use sqlite::SqliteNoteStore;
@@ -0,0 +1,289 @@
+use crate::storage::sqlite::schema::{
+    list_notes, lists, note_revisions, note_tags, notes, people, tag_definitions,
+};
+use crate::timestamp;
+use crate::types::{List, Note, NoteRevision, Person, Tag, TagDefinition, UpdateNoteRequest};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+/// SQLite has no time zones, so times are stored as naive UTC
+fn utc(time: NaiveDateTime) -> DateTime<Utc> {
+    DateTime::from_utc(time, Utc)
+}
+
+/// Our DB representation of a note. Its tags live in `note_tags`
+#[derive(Queryable, QueryableByName)]
+#[table_name = "notes"]
+pub struct DBNote {
+    pub id: i32,
+    pub uuid: String,
+    pub title: String,
+    pub owner: String,
+    pub description: String,
+    pub create_time: NaiveDateTime,
+    pub last_update_time: NaiveDateTime,
+    pub delete_time: Option<NaiveDateTime>,
+    pub version: i64,
+    pub status: String,
+}
+
+impl DBNote {
+    /// Combine a note row with the rows of its tags
+    pub fn into_note(self, note_tags: Vec<DBNoteTag>) -> anyhow::Result<Note> {
+        let uuid = Uuid::parse_str(&self.uuid).context("Parsing uuid")?;
+        let tags = note_tags
+            .into_iter()
+            .map(DBNoteTag::into_tag)
+            .collect::<anyhow::Result<HashSet<Tag>>>()?;
+        Ok(Note {
+            uuid,
+            title: self.title,
+            description: self.description,
+            create_time: utc(self.create_time),
+            last_update_time: utc(self.last_update_time),
+            delete_time: self.delete_time.map(utc),
+            tags,
+            owner: self.owner,
+            version: self.version as u64,
+            status: self.status.parse().context("Parsing status")?,
+        })
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "notes"]
+pub struct NewNote {
+    pub uuid: String,
+    pub title: String,
+    pub description: String,
+    pub create_time: NaiveDateTime,
+    pub last_update_time: NaiveDateTime,
+    pub delete_time: Option<NaiveDateTime>,
+    pub owner: String,
+    pub version: i64,
+    pub status: String,
+}
+
+impl From<&Note> for NewNote {
+    fn from(note: &Note) -> Self {
+        NewNote {
+            uuid: note.uuid.to_string(),
+            title: note.title.clone(),
+            description: note.description.clone(),
+            create_time: note.create_time.naive_utc(),
+            last_update_time: note.last_update_time.naive_utc(),
+            delete_time: note.delete_time.map(|time| time.naive_utc()),
+            owner: note.owner.clone(),
+            version: note.version as i64,
+            status: note.status.as_str().to_string(),
+        }
+    }
+}
+
+#[derive(AsChangeset)]
+#[table_name = "notes"]
+pub struct UpdateNote {
+    pub last_update_time: NaiveDateTime,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub status: Option<String>,
+    pub delete_time: Option<NaiveDateTime>,
+}
+
+impl From<&UpdateNoteRequest> for UpdateNote {
+    fn from(request: &UpdateNoteRequest) -> Self {
+        Self {
+            last_update_time: timestamp::now().naive_utc(),
+            title: request.title.clone(),
+            description: request.description.clone(),
+            status: request.status.map(|status| status.as_str().to_string()),
+            delete_time: None,
+        }
+    }
+}
+
+/// A single tag of a note, serialized as JSON, e.g. `{"RecommendedBy":"Alice"}`
+#[derive(Queryable, Insertable)]
+#[table_name = "note_tags"]
+pub struct DBNoteTag {
+    pub note_id: i32,
+    pub tag: String,
+}
+
+impl DBNoteTag {
+    pub fn for_tags(note_id: i32, tags: &HashSet<Tag>) -> anyhow::Result<Vec<DBNoteTag>> {
+        tags.iter()
+            .map(|tag| {
+                Ok(DBNoteTag {
+                    note_id,
+                    tag: serde_json::to_string(tag).context("serializing tag")?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn into_tag(self) -> anyhow::Result<Tag> {
+        serde_json::from_str(&self.tag).context("Deserializing tag")
+    }
+}
+
+/// A past version of a note. Its tags are kept together, as a JSON array
+#[derive(Queryable, Insertable)]
+#[table_name = "note_revisions"]
+pub struct DBNoteRevision {
+    pub note_id: i32,
+    pub revision: i32,
+    pub title: String,
+    pub description: String,
+    pub tags: String,
+    pub last_update_time: NaiveDateTime,
+}
+
+impl DBNoteRevision {
+    pub fn new(note_id: i32, revision: &NoteRevision) -> anyhow::Result<DBNoteRevision> {
+        Ok(DBNoteRevision {
+            note_id,
+            revision: revision.revision as i32,
+            title: revision.title.clone(),
+            description: revision.description.clone(),
+            tags: serde_json::to_string(&revision.tags).context("serializing tags")?,
+            last_update_time: revision.last_update_time.naive_utc(),
+        })
+    }
+
+    /// Revision rows only know the id of their note, so its uuid has to be given
+    pub fn into_revision(self, note_id: Uuid) -> anyhow::Result<NoteRevision> {
+        Ok(NoteRevision {
+            note_id,
+            revision: self.revision as u32,
+            title: self.title,
+            description: self.description,
+            tags: serde_json::from_str(&self.tags).context("Deserializing tags")?,
+            last_update_time: utc(self.last_update_time),
+        })
+    }
+}
+
+/// Our DB representation of a list. The notes in the list live in `list_notes`
+#[derive(Queryable)]
+pub struct DBList {
+    pub id: i32,
+    pub uuid: String,
+    pub title: String,
+    pub owner: String,
+    pub description: String,
+}
+
+impl DBList {
+    /// Combine a list row with its (already position ordered) note entries
+    pub fn into_list(self, list_notes: Vec<DBListNote>) -> anyhow::Result<List> {
+        let uuid = Uuid::parse_str(&self.uuid).context("Parsing list uuid")?;
+        let notes = list_notes
+            .into_iter()
+            .map(|list_note| Uuid::parse_str(&list_note.note_uuid).context("Parsing note uuid"))
+            .collect::<anyhow::Result<Vec<Uuid>>>()?;
+        Ok(List {
+            uuid,
+            notes,
+            title: self.title,
+            owner: self.owner,
+            description: self.description,
+        })
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[table_name = "lists"]
+pub struct NewList {
+    pub uuid: String,
+    pub title: String,
+    pub owner: String,
+    pub description: String,
+}
+
+impl From<&List> for NewList {
+    fn from(list: &List) -> Self {
+        NewList {
+            uuid: list.uuid.to_string(),
+            title: list.title.clone(),
+            owner: list.owner.clone(),
+            description: list.description.clone(),
+        }
+    }
+}
+
+/// A single entry of a list, keyed by its position in that list
+#[derive(Queryable, Insertable)]
+#[table_name = "list_notes"]
+pub struct DBListNote {
+    pub list_id: i32,
+    pub position: i32,
+    pub note_uuid: String,
+}
+
+/// A custom tag, stored as it was defined
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "tag_definitions"]
+pub struct DBTagDefinition {
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub colour: String,
+}
+
+impl From<TagDefinition> for DBTagDefinition {
+    fn from(definition: TagDefinition) -> Self {
+        DBTagDefinition {
+            owner: definition.owner,
+            name: definition.name,
+            description: definition.description,
+            colour: definition.colour,
+        }
+    }
+}
+
+impl From<DBTagDefinition> for TagDefinition {
+    fn from(definition: DBTagDefinition) -> Self {
+        TagDefinition {
+            owner: definition.owner,
+            name: definition.name,
+            description: definition.description,
+            colour: definition.colour,
+        }
+    }
+}
+
+/// A person, with their aliases kept together as a JSON array
+#[derive(Queryable, Insertable, AsChangeset)]
+#[table_name = "people"]
+pub struct DBPerson {
+    pub uuid: String,
+    pub owner: String,
+    pub name: String,
+    pub aliases: String,
+    pub description: String,
+}
+
+impl DBPerson {
+    pub fn new(person: Person) -> anyhow::Result<DBPerson> {
+        Ok(DBPerson {
+            uuid: person.uuid.to_string(),
+            owner: person.owner,
+            name: person.name,
+            aliases: serde_json::to_string(&person.aliases).context("Serializing aliases")?,
+            description: person.description,
+        })
+    }
+
+    pub fn into_person(self) -> anyhow::Result<Person> {
+        Ok(Person {
+            uuid: Uuid::parse_str(&self.uuid).context("Parsing person uuid")?,
+            owner: self.owner,
+            name: self.name,
+            aliases: serde_json::from_str(&self.aliases).context("Deserializing aliases")?,
+            description: self.description,
+        })
+    }
+}
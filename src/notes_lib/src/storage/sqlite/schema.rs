@@ -0,0 +1,83 @@
+table! {
+    list_notes (list_id, position) {
+        list_id -> Integer,
+        position -> Integer,
+        note_uuid -> Text,
+    }
+}
+
+table! {
+    lists (id) {
+        id -> Integer,
+        uuid -> Text,
+        title -> Text,
+        owner -> Text,
+        description -> Text,
+    }
+}
+
+table! {
+    note_tags (note_id, tag) {
+        note_id -> Integer,
+        tag -> Text,
+    }
+}
+
+table! {
+    note_revisions (note_id, revision) {
+        note_id -> Integer,
+        revision -> Integer,
+        title -> Text,
+        description -> Text,
+        tags -> Text,
+        last_update_time -> Timestamp,
+    }
+}
+
+table! {
+    notes (id) {
+        id -> Integer,
+        uuid -> Text,
+        title -> Text,
+        owner -> Text,
+        description -> Text,
+        create_time -> Timestamp,
+        last_update_time -> Timestamp,
+        delete_time -> Nullable<Timestamp>,
+        version -> BigInt,
+        status -> Text,
+    }
+}
+
+table! {
+    people (uuid) {
+        uuid -> Text,
+        owner -> Text,
+        name -> Text,
+        aliases -> Text,
+        description -> Text,
+    }
+}
+
+table! {
+    tag_definitions (owner, name) {
+        owner -> Text,
+        name -> Text,
+        description -> Text,
+        colour -> Text,
+    }
+}
+
+joinable!(list_notes -> lists (list_id));
+joinable!(note_revisions -> notes (note_id));
+joinable!(note_tags -> notes (note_id));
+
+allow_tables_to_appear_in_same_query!(
+    list_notes,
+    lists,
+    note_revisions,
+    note_tags,
+    notes,
+    people,
+    tag_definitions,
+);
@@ -0,0 +1,734 @@
+use super::models::{
+    DBList, DBListNote, DBNote, DBNoteRevision, DBNoteTag, DBPerson, DBTagDefinition, NewList,
+    NewNote, UpdateNote,
+};
+use super::schema::{list_notes, lists, note_revisions, note_tags, notes, people, tag_definitions};
+use crate::error::{NotesError, Result};
+use crate::storage::index::tokenize;
+use crate::storage::traits::NoteStore;
+use crate::timestamp;
+use crate::types::{
+    ArchiveNoteRequest, ArchivedNotes, FullList, List, Note, NoteRevision, NotesPage, NotesQuery,
+    Person, Tag, TagDefinition, TagFacet, TagQuery, UnarchiveNoteRequest, UpdateNoteRequest,
+};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::connection::SimpleConnection;
+use diesel::dsl::{not, sql};
+use diesel::expression::BoxableExpression;
+use diesel::r2d2::{ConnectionManager, CustomizeConnection, Pool, PoolError, PooledConnection};
+use diesel::sql_types::{Bool, Text, Timestamp};
+use diesel::sqlite::{Sqlite, SqliteConnection};
+use diesel::{
+    BoolExpressionMethods, Connection, ExpressionMethods, OptionalExtension, QueryDsl, RunQueryDsl,
+};
+use log::info;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+embed_migrations!("migrations_sqlite");
+
+/// SQLite limits how many values a statement can be given, so long lists of ids are
+/// looked up this many at a time
+const IDS_PER_QUERY: usize = 500;
+
+pub type DBPool = Pool<ConnectionManager<SqliteConnection>>;
+pub type DBCon = PooledConnection<ConnectionManager<SqliteConnection>>;
+
+/// SQLite needs foreign keys turned on for every connection, or our cascades won't run
+#[derive(Debug)]
+struct ConnectionOptions;
+
+impl CustomizeConnection<SqliteConnection, diesel::r2d2::Error> for ConnectionOptions {
+    fn on_acquire(
+        &self,
+        conn: &mut SqliteConnection,
+    ) -> std::result::Result<(), diesel::r2d2::Error> {
+        conn.batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;")
+            .map_err(diesel::r2d2::Error::QueryError)
+    }
+}
+
+pub fn create_pool(path: &str) -> std::result::Result<DBPool, PoolError> {
+    let manager = ConnectionManager::<SqliteConnection>::new(path);
+    // SQLite only allows a single writer, so there is nothing to gain from more
+    // connections. This also keeps ":memory:" databases alive across requests.
+    Pool::builder()
+        .max_size(1)
+        .connection_customizer(Box::new(ConnectionOptions))
+        .build(manager)
+}
+
+type NotesFilter = Box<dyn BoxableExpression<notes::table, Sqlite, SqlType = Bool>>;
+
+/// Translate a tag query into a condition on the `note_tags` of a note
+fn tag_filter(query: &TagQuery) -> anyhow::Result<NotesFilter> {
+    let has_tag = "EXISTS (SELECT 1 FROM note_tags \
+                   WHERE note_tags.note_id = notes.id AND note_tags.tag";
+    Ok(match query {
+        TagQuery::Tag(tag) => {
+            let tag = serde_json::to_string(tag).context("Serializing tag")?;
+            Box::new(
+                sql::<Bool>(&format!("{} = ", has_tag))
+                    .bind::<Text, _>(tag)
+                    .sql(")"),
+            )
+        }
+        // Tags with a payload are stored as {"Kind":"payload"}
+        TagQuery::AnyPayload(kind) => Box::new(
+            sql::<Bool>(&format!("{} LIKE ", has_tag))
+                .bind::<Text, _>(format!("{{\"{}\":%", kind))
+                .sql(")"),
+        ),
+        TagQuery::And(left, right) => Box::new(tag_filter(left)?.and(tag_filter(right)?)),
+        TagQuery::Or(left, right) => Box::new(tag_filter(left)?.or(tag_filter(right)?)),
+        TagQuery::Not(query) => Box::new(not(tag_filter(query)?)),
+    })
+}
+
+#[derive(Clone)]
+pub struct SqliteNoteStore {
+    db_pool: DBPool,
+}
+
+impl SqliteNoteStore {
+    /// Open (or create) the database at `path`, and bring its schema up to date
+    pub fn new(path: &str) -> SqliteNoteStore {
+        let db_pool = create_pool(path).expect("Could not open sqlite database");
+        let conn = db_pool.get().expect("Could not open sqlite database");
+        embedded_migrations::run(&conn).expect("Could not migrate sqlite database");
+        SqliteNoteStore { db_pool }
+    }
+
+    pub fn get_db_conn(&self) -> Result<DBCon, PoolError> {
+        self.db_pool.get()
+    }
+}
+
+impl NoteStore for SqliteNoteStore {
+    fn get_note(&self, id: Uuid) -> Result<Note> {
+        let conn = self.get_db_conn()?;
+        info!("Looking for note {}", id);
+        let db_note = notes::dsl::notes
+            .filter(notes::dsl::uuid.eq(id.to_string()))
+            .first::<DBNote>(&conn)
+            .optional()
+            .context(format!("Looking for note with id {}", id))?
+            .ok_or_else(|| NotesError::NotFound(format!("No note found for id {}", id)))?;
+        Ok(load_tags(&conn, vec![db_note])?.remove(0))
+    }
+
+    fn get_notes(&self, owner: String, query: NotesQuery) -> Result<NotesPage> {
+        let conn = self.get_db_conn()?;
+        let key = query.sort.column();
+        let mut db_query = notes::dsl::notes
+            .filter(notes::dsl::owner.eq(&owner))
+            .order(sql::<Text>(&format!("{}, uuid", key)))
+            .into_boxed();
+        db_query = match query.archived {
+            ArchivedNotes::Exclude => db_query.filter(notes::dsl::delete_time.is_null()),
+            ArchivedNotes::Include => db_query,
+            ArchivedNotes::Only => db_query.filter(notes::dsl::delete_time.is_not_null()),
+        };
+        if let Some(status) = query.status {
+            db_query = db_query.filter(notes::dsl::status.eq(status.as_str()));
+        }
+        if let Some(tags) = &query.tags {
+            db_query = db_query.filter(tag_filter(tags)?);
+        }
+        if let Some(since) = query.since {
+            db_query = db_query.filter(notes::dsl::create_time.ge(since.naive_utc()));
+        }
+        if let Some(until) = query.until {
+            db_query = db_query.filter(notes::dsl::create_time.lt(until.naive_utc()));
+        }
+        if let Some(after) = &query.after {
+            let after_key = sql::<Bool>(&format!("({}, uuid) > (", key));
+            db_query = match after.time()? {
+                Some(time) => db_query.filter(
+                    after_key
+                        .bind::<Timestamp, _>(time.naive_utc())
+                        .sql(", ")
+                        .bind::<Text, _>(after.uuid.to_string())
+                        .sql(")"),
+                ),
+                None => db_query.filter(
+                    after_key
+                        .bind::<Text, _>(&after.key)
+                        .sql(", ")
+                        .bind::<Text, _>(after.uuid.to_string())
+                        .sql(")"),
+                ),
+            };
+        }
+        if let Some(limit) = query.limit {
+            // One more than asked for tells NotesPage whether there is a next page
+            db_query = db_query.limit(limit as i64 + 1);
+        }
+        let db_notes = db_query
+            .load::<DBNote>(&conn)
+            .context(format!("Looking for owner {}", owner))?;
+        let notes = load_tags(&conn, db_notes).context(format!("Reading notes for {}", owner))?;
+        Ok(NotesPage::new(notes, query.sort, query.limit))
+    }
+
+    fn search_notes(&self, owner: String, query: String) -> Result<Vec<Note>> {
+        // Quote every word, so nothing in the query is read as FTS5 syntax
+        let words: Vec<String> = tokenize(&query)
+            .into_iter()
+            .map(|word| format!("\"{}\"", word))
+            .collect();
+        if words.is_empty() {
+            return Ok(Vec::new());
+        }
+        let conn = self.get_db_conn()?;
+        // bm25 scores are lower for better matches. Titles count ten times as much as descriptions,
+        // and ties are broken the same way as the memory store, by title and then uuid.
+        let db_notes = diesel::sql_query(
+            "SELECT notes.id, notes.uuid, notes.title, notes.owner, notes.description, \
+                    notes.create_time, notes.last_update_time, notes.delete_time, notes.version, \
+                    notes.status \
+             FROM notes JOIN notes_fts ON notes_fts.rowid = notes.id \
+             WHERE notes_fts MATCH ? AND notes.owner = ? \
+             ORDER BY bm25(notes_fts, 10.0, 1.0), notes.title, notes.uuid",
+        )
+        .bind::<Text, _>(words.join(" "))
+        .bind::<Text, _>(&owner)
+        .load::<DBNote>(&conn)
+        .context(format!("Searching notes of {} for {}", owner, query))?;
+        Ok(load_tags(&conn, db_notes).context(format!("Reading notes for {}", owner))?)
+    }
+
+    fn tag_facets(&self, owner: String) -> Result<Vec<TagFacet>> {
+        let conn = self.get_db_conn()?;
+        let db_note_tags = note_tags::table
+            .inner_join(notes::table)
+            .filter(notes::dsl::owner.eq(&owner))
+            .filter(notes::dsl::delete_time.is_null())
+            .select((note_tags::dsl::note_id, note_tags::dsl::tag))
+            .load::<DBNoteTag>(&conn)
+            .context(format!("Looking for tags of {}", owner))?;
+        let mut tags: HashMap<i32, HashSet<Tag>> = HashMap::new();
+        for note_tag in db_note_tags {
+            let note_id = note_tag.note_id;
+            tags.entry(note_id)
+                .or_default()
+                .insert(note_tag.into_tag()?);
+        }
+        Ok(TagFacet::count(tags.values()))
+    }
+
+    fn create_note(&mut self, note: Note) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        conn.transaction::<_, anyhow::Error, _>(|| {
+            diesel::insert_into(notes::table)
+                .values(&NewNote::from(&note))
+                .execute(&conn)
+                .context(format!(
+                    "Error attempting to persist note in db with uuid {}",
+                    note.uuid
+                ))?;
+            let note_id = find_note_id(&conn, note.uuid)?
+                .context(format!("Looking up inserted note with uuid {}", note.uuid))?;
+            insert_tags(&conn, note_id, &note.tags)
+                .context(format!("Storing tags for note with uuid {}", note.uuid))?;
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn update_note(&mut self, request: UpdateNoteRequest) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        conn.transaction(|| update_note_in(&conn, &request))?
+    }
+
+    fn rename_tag(
+        &mut self,
+        owner: String,
+        from: Tag,
+        to: Tag,
+        dry_run: bool,
+    ) -> Result<Vec<Note>> {
+        let conn = self.get_db_conn()?;
+        let notes = conn.transaction::<_, anyhow::Error, _>(|| {
+            let db_notes = notes::dsl::notes
+                .filter(notes::dsl::owner.eq(&owner))
+                .filter(tag_filter(&TagQuery::Tag(from.clone()))?)
+                .load::<DBNote>(&conn)
+                .context(format!("Looking for notes of {} tagged {:?}", owner, from))?;
+            let notes = load_tags(&conn, db_notes).context("Reading notes to retag")?;
+            if dry_run {
+                return Ok(notes);
+            }
+            for note in &notes {
+                let mut tags = note.tags.clone();
+                tags.remove(&from);
+                tags.insert(to.clone());
+                let request = UpdateNoteRequest {
+                    note_id: note.uuid,
+                    tags: Some(tags),
+                    ..UpdateNoteRequest::default()
+                };
+                // Fail the transaction itself, so notes already retagged are rolled back
+                update_note_in(&conn, &request)?.map_err(anyhow::Error::from)?;
+            }
+            Ok(notes)
+        })?;
+        Ok(notes)
+    }
+
+    fn get_note_history(&self, id: Uuid) -> Result<Vec<NoteRevision>> {
+        let conn = self.get_db_conn()?;
+        let note_id = find_note_id(&conn, id)?
+            .ok_or_else(|| NotesError::NotFound(format!("No note found for id {}", id)))?;
+        let revisions = note_revisions::dsl::note_revisions
+            .filter(note_revisions::dsl::note_id.eq(note_id))
+            .order(note_revisions::dsl::revision)
+            .load::<DBNoteRevision>(&conn)
+            .context(format!("Loading history of note {}", id))?;
+        Ok(revisions
+            .into_iter()
+            .map(|revision| revision.into_revision(id))
+            .collect::<anyhow::Result<Vec<NoteRevision>>>()
+            .context("Reading revisions")?)
+    }
+
+    fn archive_note(&mut self, request: ArchiveNoteRequest) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let now = timestamp::now().naive_utc();
+        let update = UpdateNote {
+            last_update_time: now,
+            title: None,
+            description: None,
+            status: None,
+            delete_time: Some(now),
+        };
+        let updated = diesel::update(
+            notes::dsl::notes.filter(notes::dsl::uuid.eq(request.note_id.to_string())),
+        )
+        .set((&update, notes::dsl::version.eq(notes::dsl::version + 1)))
+        .execute(&conn)
+        .context("Archiving note")?;
+        if updated == 0 {
+            return Err(NotesError::NotFound(format!(
+                "No note found for id {}",
+                request.note_id
+            )));
+        }
+        Ok(())
+    }
+
+    fn unarchive_note(&mut self, request: UnarchiveNoteRequest) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let now = timestamp::now().naive_utc();
+        let updated = diesel::update(
+            notes::dsl::notes.filter(notes::dsl::uuid.eq(request.note_id.to_string())),
+        )
+        .set((
+            notes::dsl::last_update_time.eq(now),
+            notes::dsl::delete_time.eq(None::<NaiveDateTime>),
+            notes::dsl::version.eq(notes::dsl::version + 1),
+        ))
+        .execute(&conn)
+        .context("Unarchiving note")?;
+        if updated == 0 {
+            return Err(NotesError::NotFound(format!(
+                "No note found for id {}",
+                request.note_id
+            )));
+        }
+        Ok(())
+    }
+
+    fn purge_note(&mut self, id: Uuid) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        // The note's tags and history are removed by the ON DELETE CASCADE
+        let deleted = conn.transaction::<_, anyhow::Error, _>(|| {
+            remove_from_lists(&conn, id)?;
+            let deleted =
+                diesel::delete(notes::dsl::notes.filter(notes::dsl::uuid.eq(id.to_string())))
+                    .execute(&conn)
+                    .context(format!("Deleting note with id {}", id))?;
+            Ok(deleted)
+        })?;
+        if deleted == 0 {
+            return Err(NotesError::NotFound(format!("No note found for id {}", id)));
+        }
+        Ok(())
+    }
+
+    fn purge_archived_before(&mut self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<Vec<Note>> {
+        let conn = self.get_db_conn()?;
+        let notes = conn.transaction::<_, anyhow::Error, _>(|| {
+            let db_notes = notes::dsl::notes
+                .filter(notes::dsl::delete_time.lt(cutoff.naive_utc()))
+                .load::<DBNote>(&conn)
+                .context("Looking for expired notes")?;
+            let notes = load_tags(&conn, db_notes).context("Reading expired notes")?;
+            if dry_run {
+                return Ok(notes);
+            }
+            // One at a time, as every note also has to be taken out of its lists
+            for note in &notes {
+                remove_from_lists(&conn, note.uuid)?;
+                diesel::delete(
+                    notes::dsl::notes.filter(notes::dsl::uuid.eq(note.uuid.to_string())),
+                )
+                .execute(&conn)
+                .context(format!("Deleting note with id {}", note.uuid))?;
+            }
+            Ok(notes)
+        })?;
+        Ok(notes)
+    }
+
+    fn get_list(&self, id: Uuid) -> Result<List> {
+        let conn = self.get_db_conn()?;
+        load_list(&conn, id)
+    }
+
+    fn get_full_list(&self, id: Uuid) -> Result<FullList> {
+        let conn = self.get_db_conn()?;
+        let list = load_list(&conn, id)?;
+        let note_ids: Vec<String> = list
+            .notes
+            .iter()
+            .map(|note_id| note_id.to_string())
+            .collect();
+        let mut db_notes = Vec::new();
+        for chunk in note_ids.chunks(IDS_PER_QUERY) {
+            db_notes.extend(
+                notes::dsl::notes
+                    .filter(notes::dsl::uuid.eq_any(chunk))
+                    .load::<DBNote>(&conn)
+                    .context(format!("Looking for notes in list {}", id))?,
+            );
+        }
+        let notes_in_list: HashMap<Uuid, Note> = load_tags(&conn, db_notes)?
+            .into_iter()
+            .map(|note| (note.uuid, note))
+            .collect();
+        for note_id in &list.notes {
+            if !notes_in_list.contains_key(note_id) {
+                return Err(NotesError::NotFound(format!(
+                    "No note found for id {} in list {}",
+                    note_id, id
+                )));
+            }
+        }
+        Ok(FullList {
+            list,
+            notes_in_list,
+        })
+    }
+
+    fn get_lists(&self, owner: String) -> Result<HashMap<Uuid, List>> {
+        let conn = self.get_db_conn()?;
+        let db_lists = lists::dsl::lists
+            .filter(lists::dsl::owner.eq(&owner))
+            .load::<DBList>(&conn)
+            .context(format!("Looking for lists of owner {}", owner))?;
+        let list_ids: Vec<i32> = db_lists.iter().map(|db_list| db_list.id).collect();
+        let mut db_list_notes: HashMap<i32, Vec<DBListNote>> = HashMap::new();
+        for chunk in list_ids.chunks(IDS_PER_QUERY) {
+            for list_note in list_notes::dsl::list_notes
+                .filter(list_notes::dsl::list_id.eq_any(chunk))
+                .order(list_notes::dsl::position)
+                .load::<DBListNote>(&conn)
+                .context(format!("Looking for notes in lists of owner {}", owner))?
+            {
+                db_list_notes
+                    .entry(list_note.list_id)
+                    .or_default()
+                    .push(list_note);
+            }
+        }
+
+        let mut resulting_map = HashMap::new();
+        for db_list in db_lists {
+            let entries = db_list_notes.remove(&db_list.id).unwrap_or_default();
+            let list = db_list
+                .into_list(entries)
+                .context(format!("Reading lists for {}", owner))?;
+            resulting_map.insert(list.uuid, list);
+        }
+        Ok(resulting_map)
+    }
+
+    fn store_list(&mut self, list: List) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let new_list = NewList::from(&list);
+        conn.transaction::<_, anyhow::Error, _>(|| {
+            // Lists are stored whole, so upsert the list itself and replace its entries
+            let existing = lists::dsl::lists
+                .filter(lists::dsl::uuid.eq(&new_list.uuid))
+                .select(lists::dsl::id)
+                .first::<i32>(&conn)
+                .optional()
+                .context(format!("Looking for list with uuid {}", list.uuid))?;
+            let list_id = match existing {
+                Some(list_id) => {
+                    diesel::update(lists::dsl::lists.find(list_id))
+                        .set(&new_list)
+                        .execute(&conn)
+                        .context(format!("Storing list with uuid {}", list.uuid))?;
+                    list_id
+                }
+                None => {
+                    diesel::insert_into(lists::table)
+                        .values(&new_list)
+                        .execute(&conn)
+                        .context(format!("Storing list with uuid {}", list.uuid))?;
+                    lists::dsl::lists
+                        .filter(lists::dsl::uuid.eq(&new_list.uuid))
+                        .select(lists::dsl::id)
+                        .first::<i32>(&conn)
+                        .context(format!("Looking for list with uuid {}", list.uuid))?
+                }
+            };
+            diesel::delete(
+                list_notes::dsl::list_notes.filter(list_notes::dsl::list_id.eq(list_id)),
+            )
+            .execute(&conn)
+            .context(format!("Clearing notes of list with uuid {}", list.uuid))?;
+            // SQLite can't insert several rows in one statement through diesel
+            for (position, note_id) in list.notes.iter().enumerate() {
+                diesel::insert_into(list_notes::table)
+                    .values(&DBListNote {
+                        list_id,
+                        position: position as i32,
+                        note_uuid: note_id.to_string(),
+                    })
+                    .execute(&conn)
+                    .context(format!("Storing notes of list with uuid {}", list.uuid))?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+
+    fn delete_list(&mut self, id: Uuid) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        // The list's entries in list_notes are removed by the ON DELETE CASCADE
+        let deleted = diesel::delete(lists::dsl::lists.filter(lists::dsl::uuid.eq(id.to_string())))
+            .execute(&conn)
+            .context(format!("Deleting list with id {}", id))?;
+        if deleted == 0 {
+            return Err(NotesError::NotFound(format!("No list found for id {}", id)));
+        }
+        Ok(())
+    }
+
+    fn get_tag_definitions(&self, owner: String) -> Result<Vec<TagDefinition>> {
+        let conn = self.get_db_conn()?;
+        let definitions = tag_definitions::dsl::tag_definitions
+            .filter(tag_definitions::dsl::owner.eq(&owner))
+            .order(tag_definitions::dsl::name)
+            .load::<DBTagDefinition>(&conn)
+            .context(format!("Looking for tags defined by {}", owner))?;
+        Ok(definitions.into_iter().map(TagDefinition::from).collect())
+    }
+
+    fn store_tag_definition(&mut self, definition: TagDefinition) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let name = definition.name.clone();
+        diesel::replace_into(tag_definitions::table)
+            .values(&DBTagDefinition::from(definition))
+            .execute(&conn)
+            .context(format!("Storing tag {}", name))?;
+        Ok(())
+    }
+
+    fn get_people(&self, owner: String) -> Result<Vec<Person>> {
+        let conn = self.get_db_conn()?;
+        let people = people::dsl::people
+            .filter(people::dsl::owner.eq(&owner))
+            .order((people::dsl::name, people::dsl::uuid))
+            .load::<DBPerson>(&conn)
+            .context(format!("Looking for people of {}", owner))?;
+        Ok(people
+            .into_iter()
+            .map(DBPerson::into_person)
+            .collect::<anyhow::Result<Vec<Person>>>()
+            .context("Reading people")?)
+    }
+
+    fn get_person(&self, id: Uuid) -> Result<Person> {
+        let conn = self.get_db_conn()?;
+        let person = people::dsl::people
+            .find(id.to_string())
+            .first::<DBPerson>(&conn)
+            .optional()
+            .context(format!("Looking for person with id {}", id))?
+            .ok_or_else(|| NotesError::NotFound(format!("No person found for id {}", id)))?;
+        Ok(person.into_person()?)
+    }
+
+    fn store_person(&mut self, person: Person) -> Result<()> {
+        let conn = self.get_db_conn()?;
+        let id = person.uuid;
+        diesel::replace_into(people::table)
+            .values(&DBPerson::new(person)?)
+            .execute(&conn)
+            .context(format!("Storing person {}", id))?;
+        Ok(())
+    }
+}
+
+/// Find the row id of a note, which its tags are keyed by
+fn find_note_id(conn: &DBCon, id: Uuid) -> anyhow::Result<Option<i32>> {
+    notes::dsl::notes
+        .filter(notes::dsl::uuid.eq(id.to_string()))
+        .select(notes::dsl::id)
+        .first::<i32>(conn)
+        .optional()
+        .context(format!("Looking for note with id {}", id))
+}
+
+/// Update a note within an open transaction. A note that is missing or has moved on
+/// is an answer rather than a failure, so it comes back as the inner Result
+fn update_note_in(conn: &DBCon, request: &UpdateNoteRequest) -> anyhow::Result<Result<()>> {
+    let note_id = match find_note_id(conn, request.note_id)? {
+        Some(note_id) => note_id,
+        None => {
+            return Ok(Err(NotesError::NotFound(format!(
+                "No note found for id {}",
+                request.note_id
+            ))))
+        }
+    };
+    let db_note = notes::dsl::notes
+        .find(note_id)
+        .first::<DBNote>(conn)
+        .context("Loading note")?;
+    let current = load_tags(conn, vec![db_note])?
+        .pop()
+        .context("Loading tags of note")?;
+    let expected = request.expected_version.unwrap_or(current.version);
+    let updated = diesel::update(
+        notes::dsl::notes
+            .find(note_id)
+            .filter(notes::dsl::version.eq(expected as i64)),
+    )
+    .set((
+        &UpdateNote::from(request),
+        notes::dsl::version.eq(notes::dsl::version + 1),
+    ))
+    .execute(conn)
+    .context("Updating note")?;
+    if updated == 0 {
+        return Ok(Err(NotesError::Conflict(format!(
+            "Note {} is at version {}, not {}",
+            request.note_id, current.version, expected
+        ))));
+    }
+    let latest = note_revisions::dsl::note_revisions
+        .filter(note_revisions::dsl::note_id.eq(note_id))
+        .select(note_revisions::dsl::revision)
+        .order(note_revisions::dsl::revision.desc())
+        .first::<i32>(conn)
+        .optional()
+        .context("Looking for the latest revision")?;
+    let revision = NoteRevision::of(&current, latest.unwrap_or(0) as u32 + 1);
+    diesel::insert_into(note_revisions::table)
+        .values(&DBNoteRevision::new(note_id, &revision)?)
+        .execute(conn)
+        .context("Recording revision")?;
+    if let Some(tags) = &request.tags {
+        diesel::delete(note_tags::dsl::note_tags.filter(note_tags::dsl::note_id.eq(note_id)))
+            .execute(conn)
+            .context("Clearing tags of note")?;
+        insert_tags(conn, note_id, tags).context("Storing tags of note")?;
+    }
+    Ok(Ok(()))
+}
+
+fn insert_tags(conn: &DBCon, note_id: i32, tags: &HashSet<Tag>) -> anyhow::Result<()> {
+    for note_tag in DBNoteTag::for_tags(note_id, tags)? {
+        diesel::insert_into(note_tags::table)
+            .values(&note_tag)
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Fill in the tags of each note, keeping the order of `db_notes`
+fn load_tags(conn: &DBCon, db_notes: Vec<DBNote>) -> anyhow::Result<Vec<Note>> {
+    let note_ids: Vec<i32> = db_notes.iter().map(|db_note| db_note.id).collect();
+    let mut db_note_tags: HashMap<i32, Vec<DBNoteTag>> = HashMap::new();
+    for chunk in note_ids.chunks(IDS_PER_QUERY) {
+        for note_tag in note_tags::dsl::note_tags
+            .filter(note_tags::dsl::note_id.eq_any(chunk))
+            .load::<DBNoteTag>(conn)
+            .context("Looking for tags of notes")?
+        {
+            db_note_tags
+                .entry(note_tag.note_id)
+                .or_default()
+                .push(note_tag);
+        }
+    }
+    db_notes
+        .into_iter()
+        .map(|db_note| {
+            let tags = db_note_tags.remove(&db_note.id).unwrap_or_default();
+            db_note.into_note(tags).context("reading db note")
+        })
+        .collect()
+}
+
+/// Take a note out of every list it is in, closing the gaps it leaves behind
+fn remove_from_lists(conn: &DBCon, id: Uuid) -> anyhow::Result<()> {
+    let list_ids: Vec<i32> = list_notes::dsl::list_notes
+        .filter(list_notes::dsl::note_uuid.eq(id.to_string()))
+        .select(list_notes::dsl::list_id)
+        .distinct()
+        .load(conn)
+        .context(format!("Looking for lists with note {}", id))?;
+    if list_ids.is_empty() {
+        return Ok(());
+    }
+    let mut kept = Vec::new();
+    for chunk in list_ids.chunks(IDS_PER_QUERY) {
+        kept.extend(
+            list_notes::dsl::list_notes
+                .filter(list_notes::dsl::list_id.eq_any(chunk))
+                .filter(list_notes::dsl::note_uuid.ne(id.to_string()))
+                .order((list_notes::dsl::list_id, list_notes::dsl::position))
+                .load::<DBListNote>(conn)
+                .context("Looking for the rest of the lists")?,
+        );
+        diesel::delete(list_notes::dsl::list_notes.filter(list_notes::dsl::list_id.eq_any(chunk)))
+            .execute(conn)
+            .context("Clearing lists")?;
+    }
+    let mut next_positions: HashMap<i32, i32> = HashMap::new();
+    for entry in kept {
+        let next_position = next_positions.entry(entry.list_id).or_default();
+        let position = *next_position;
+        *next_position += 1;
+        diesel::insert_into(list_notes::table)
+            .values(&DBListNote { position, ..entry })
+            .execute(conn)
+            .context("Storing the rest of the lists")?;
+    }
+    Ok(())
+}
+
+/// Load a list and its ordered note ids
+fn load_list(conn: &DBCon, id: Uuid) -> Result<List> {
+    let db_list = lists::dsl::lists
+        .filter(lists::dsl::uuid.eq(id.to_string()))
+        .first::<DBList>(conn)
+        .optional()
+        .context(format!("Looking for list with id {}", id))?
+        .ok_or_else(|| NotesError::NotFound(format!("No list found for id {}", id)))?;
+    let db_list_notes = list_notes::dsl::list_notes
+        .filter(list_notes::dsl::list_id.eq(db_list.id))
+        .order(list_notes::dsl::position)
+        .load::<DBListNote>(conn)
+        .context(format!("Looking for notes in list {}", id))?;
+    Ok(db_list
+        .into_list(db_list_notes)
+        .context("reading db list")?)
+}
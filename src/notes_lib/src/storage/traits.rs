@@ -1,15 +1,49 @@
-use crate::types::{ArchiveNoteRequest, FullList, List, Note, UpdateNoteRequest};
-use anyhow::Result;
+use crate::error::Result;
+use crate::types::{
+    ArchiveNoteRequest, FullList, List, Note, NoteRevision, NotesPage, NotesQuery, Person, Tag,
+    TagDefinition, TagFacet, UnarchiveNoteRequest, UpdateNoteRequest,
+};
+use chrono::{DateTime, Utc};
 use std::collections::HashMap;
 use uuid::Uuid;
 
 pub trait NoteStore: Send + Sync + Clone + 'static {
     fn get_note(&self, id: Uuid) -> Result<Note>;
-    fn get_notes(&self, owner: String) -> Result<HashMap<Uuid, Note>>;
+    /// A page of the notes of `owner` that pass the filters of `query`
+    fn get_notes(&self, owner: String, query: NotesQuery) -> Result<NotesPage>;
+    /// Notes of `owner` whose title or description contain every word of `query`, best match first
+    fn search_notes(&self, owner: String, query: String) -> Result<Vec<Note>>;
+    /// How many of the notes of `owner` have each tag, leaving out archived notes
+    fn tag_facets(&self, owner: String) -> Result<Vec<TagFacet>>;
     fn create_note(&mut self, note: Note) -> Result<()>;
+    /// Replace the tag `from` with `to` on every note of `owner`, archived or not, all at once.
+    /// Each retagged note gets a new revision, like any other update. Returns the retagged
+    /// notes as they were before. With `dry_run`, only returns the notes that would be retagged
+    fn rename_tag(&mut self, owner: String, from: Tag, to: Tag, dry_run: bool)
+        -> Result<Vec<Note>>;
+    /// Update a note, keeping the version it replaces as its next revision
     fn update_note(&mut self, update_request: UpdateNoteRequest) -> Result<()>;
+    /// Every past version of a note, oldest first
+    fn get_note_history(&self, id: Uuid) -> Result<Vec<NoteRevision>>;
     fn archive_note(&mut self, archive_request: ArchiveNoteRequest) -> Result<()>;
+    fn unarchive_note(&mut self, unarchive_request: UnarchiveNoteRequest) -> Result<()>;
+    /// Delete a note for good, and take it out of every list it is in
+    fn purge_note(&mut self, id: Uuid) -> Result<()>;
+    /// Purge every note, of any owner, that was archived before `cutoff`.
+    /// Returns the purged notes. With `dry_run`, only returns the notes that would be purged
+    fn purge_archived_before(&mut self, cutoff: DateTime<Utc>, dry_run: bool) -> Result<Vec<Note>>;
+    fn get_list(&self, id: Uuid) -> Result<List>;
     fn get_lists(&self, owner: String) -> Result<HashMap<Uuid, List>>;
     fn get_full_list(&self, id: Uuid) -> Result<FullList>;
     fn store_list(&mut self, list: List) -> Result<()>;
+    fn delete_list(&mut self, id: Uuid) -> Result<()>;
+    /// The custom tags `owner` has defined, ordered by name
+    fn get_tag_definitions(&self, owner: String) -> Result<Vec<TagDefinition>>;
+    /// Define a custom tag, replacing any definition of the same name by the same owner
+    fn store_tag_definition(&mut self, definition: TagDefinition) -> Result<()>;
+    /// The people `owner` has added, ordered by name
+    fn get_people(&self, owner: String) -> Result<Vec<Person>>;
+    fn get_person(&self, id: Uuid) -> Result<Person>;
+    /// Add a person, replacing any person with the same id
+    fn store_person(&mut self, person: Person) -> Result<()>;
 }
@@ -0,0 +1,284 @@
+//! Parsing and evaluation of `TagQuery`s.
+//!
+//! The syntax is a boolean expression over tags, e.g.
+//! `Book AND (Productivity OR Career) AND RecommendedBy("Alice") AND NOT Origin(*)`.
+//! `NOT` binds tightest, then `AND`, then `OR`. Keywords are case insensitive, tag names are not.
+//! Payloads are double quoted strings where `\"` and `\\` escape a quote and a backslash,
+//! and `*` in place of a payload matches any payload.
+//! Queries are evaluated recursively, so they can only be `MAX_DEPTH` levels deep.
+use crate::error::{NotesError, Result};
+use crate::types::{Tag, TagQuery};
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Text(String),
+    Star,
+    Open,
+    Close,
+}
+
+/// How deeply a query can nest, counting every `AND`, `OR`, `NOT` and parenthesis
+pub const MAX_DEPTH: usize = 64;
+
+fn invalid(message: String) -> NotesError {
+    NotesError::InvalidInput(format!("Invalid tag query: {}", message))
+}
+
+fn lex(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' | '*' => {
+                chars.next();
+                tokens.push(match c {
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => Token::Star,
+                });
+            }
+            '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => text.push(escaped),
+                            _ => {
+                                return Err(invalid(
+                                    "only \\\" and \\\\ can be escaped".to_string(),
+                                ))
+                            }
+                        },
+                        Some(c) => text.push(c),
+                        None => return Err(invalid("unterminated string".to_string())),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => return Err(invalid(format!("unexpected '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// How many parentheses and `NOT`s we are inside of
+    nesting: usize,
+}
+
+/// A query and how deep its tree is
+type Parsed = (TagQuery, usize);
+
+/// Fail if a query `depth` levels deep is too deep to evaluate
+fn check_depth(depth: usize) -> Result<usize> {
+    if depth > MAX_DEPTH {
+        return Err(invalid(format!(
+            "queries can't nest more than {} deep",
+            MAX_DEPTH
+        )));
+    }
+    Ok(depth)
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(invalid(format!("expected {}", what))),
+        }
+    }
+
+    /// Consume the next token if it is the keyword `keyword`
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword) => {
+                self.position += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Parse what a parenthesis or `NOT` wraps, without recursing too deeply to return
+    fn nested(&mut self, parse: fn(&mut Parser) -> Result<Parsed>) -> Result<Parsed> {
+        self.nesting += 1;
+        check_depth(self.nesting)?;
+        let parsed = parse(self)?;
+        self.nesting -= 1;
+        Ok(parsed)
+    }
+
+    fn or(&mut self) -> Result<Parsed> {
+        let (mut query, mut depth) = self.and()?;
+        while self.keyword("OR") {
+            let (right, right_depth) = self.and()?;
+            depth = check_depth(depth.max(right_depth) + 1)?;
+            query = TagQuery::Or(Box::new(query), Box::new(right));
+        }
+        Ok((query, depth))
+    }
+
+    fn and(&mut self) -> Result<Parsed> {
+        let (mut query, mut depth) = self.unary()?;
+        while self.keyword("AND") {
+            let (right, right_depth) = self.unary()?;
+            depth = check_depth(depth.max(right_depth) + 1)?;
+            query = TagQuery::And(Box::new(query), Box::new(right));
+        }
+        Ok((query, depth))
+    }
+
+    fn unary(&mut self) -> Result<Parsed> {
+        if self.keyword("NOT") {
+            let (query, depth) = self.nested(Parser::unary)?;
+            return Ok((TagQuery::Not(Box::new(query)), check_depth(depth + 1)?));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Parsed> {
+        match self.next() {
+            Some(Token::Open) => {
+                let parsed = self.nested(Parser::or)?;
+                self.expect(Token::Close, "')'")?;
+                Ok(parsed)
+            }
+            Some(Token::Word(kind)) => {
+                if self.peek() != Some(&Token::Open) {
+                    return Tag::from_parts(&kind, None)
+                        .map(|tag| (TagQuery::Tag(tag), 1))
+                        .ok_or_else(|| {
+                            invalid(format!("{} is not a tag without a payload", kind))
+                        });
+                }
+                self.position += 1;
+                let query = match self.next() {
+                    Some(Token::Star) => {
+                        // Any payload will do to check that this variant has one
+                        Tag::from_parts(&kind, Some("")).ok_or_else(|| {
+                            invalid(format!("{} is not a tag with a payload", kind))
+                        })?;
+                        TagQuery::AnyPayload(kind)
+                    }
+                    Some(Token::Text(payload)) => Tag::from_parts(&kind, Some(&payload))
+                        .map(TagQuery::Tag)
+                        .ok_or_else(|| invalid(format!("{} is not a tag with a payload", kind)))?,
+                    _ => return Err(invalid(format!("expected a string or * after {}(", kind))),
+                };
+                self.expect(Token::Close, "')'")?;
+                Ok((query, 1))
+            }
+            Some(token) => Err(invalid(format!("unexpected {:?}", token))),
+            None => Err(invalid("unexpected end of query".to_string())),
+        }
+    }
+}
+
+/// Parse a tag query, reporting mistakes as `InvalidInput`
+pub fn parse(input: &str) -> Result<TagQuery> {
+    let mut parser = Parser {
+        tokens: lex(input)?,
+        position: 0,
+        nesting: 0,
+    };
+    let (query, _) = parser.or()?;
+    match parser.peek() {
+        None => Ok(query),
+        Some(token) => Err(invalid(format!("unexpected {:?}", token))),
+    }
+}
+
+impl FromStr for TagQuery {
+    type Err = NotesError;
+
+    fn from_str(input: &str) -> Result<TagQuery> {
+        parse(input)
+    }
+}
+
+/// Writes the query back out in a form that `parse` reads
+impl fmt::Display for TagQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TagQuery::Tag(tag) => match tag.payload() {
+                Some(payload) => {
+                    let escaped = payload.replace('\\', "\\\\").replace('"', "\\\"");
+                    write!(f, "{}(\"{}\")", tag.kind(), escaped)
+                }
+                None => write!(f, "{}", tag.kind()),
+            },
+            TagQuery::AnyPayload(kind) => write!(f, "{}(*)", kind),
+            TagQuery::And(left, right) => write!(f, "({} AND {})", left, right),
+            TagQuery::Or(left, right) => write!(f, "({} OR {})", left, right),
+            TagQuery::Not(query) => write!(f, "NOT {}", query),
+        }
+    }
+}
+
+impl TagQuery {
+    /// Widen every tag in the query to also match the tags that imply it,
+    /// so `NOT Entertainment` excludes `Podcast`s too. See `Tag::parent`
+    pub fn expand(self) -> TagQuery {
+        match self {
+            TagQuery::Tag(tag) => {
+                let descendants = tag.descendants();
+                descendants
+                    .into_iter()
+                    .fold(TagQuery::Tag(tag), |query, descendant| {
+                        TagQuery::Or(Box::new(query), Box::new(TagQuery::Tag(descendant)))
+                    })
+            }
+            TagQuery::AnyPayload(kind) => TagQuery::AnyPayload(kind),
+            TagQuery::And(left, right) => {
+                TagQuery::And(Box::new(left.expand()), Box::new(right.expand()))
+            }
+            TagQuery::Or(left, right) => {
+                TagQuery::Or(Box::new(left.expand()), Box::new(right.expand()))
+            }
+            TagQuery::Not(query) => TagQuery::Not(Box::new(query.expand())),
+        }
+    }
+
+    /// Whether a note with these tags matches the query
+    pub fn matches(&self, tags: &HashSet<Tag>) -> bool {
+        match self {
+            TagQuery::Tag(tag) => tags.contains(tag),
+            TagQuery::AnyPayload(kind) => tags.iter().any(|tag| tag.kind() == kind),
+            TagQuery::And(left, right) => left.matches(tags) && right.matches(tags),
+            TagQuery::Or(left, right) => left.matches(tags) || right.matches(tags),
+            TagQuery::Not(query) => !query.matches(tags),
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Note timestamps, and how they are written to and read from JSON.
+//!
+//! Timestamps are written as RFC 3339, e.g. `2021-01-01T00:00:00Z`. Notes used to
+//! carry strings of unix seconds instead, so both are accepted when reading, and
+//! responses can be written the old way for clients that still expect it.
+use crate::error::{NotesError, Result};
+use chrono::{DateTime, NaiveDateTime, SecondsFormat, SubsecRound, TimeZone, Utc};
+use serde::{de, Deserialize, Deserializer, Serializer};
+use std::cell::Cell;
+
+/// How timestamps are written to JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    Rfc3339,
+    /// A string of unix seconds, like `"1609459200"`
+    EpochSeconds,
+}
+
+impl Default for TimestampFormat {
+    fn default() -> Self {
+        TimestampFormat::Rfc3339
+    }
+}
+
+thread_local! {
+    static FORMAT: Cell<TimestampFormat> = const { Cell::new(TimestampFormat::Rfc3339) };
+}
+
+/// Puts the previous format back when dropped, even if serializing panicked
+struct RestoreFormat(TimestampFormat);
+
+impl Drop for RestoreFormat {
+    fn drop(&mut self) {
+        FORMAT.with(|current| current.set(self.0));
+    }
+}
+
+/// Serialize timestamps in `format` for the duration of `serialize`
+pub fn with_format<T>(format: TimestampFormat, serialize: impl FnOnce() -> T) -> T {
+    let _restore = RestoreFormat(FORMAT.with(|current| current.replace(format)));
+    serialize()
+}
+
+/// The current time, only as precise as Postgres keeps it
+pub fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(6)
+}
+
+/// The time `seconds` after the unix epoch
+pub fn from_epoch_seconds(seconds: i64) -> DateTime<Utc> {
+    Utc.timestamp(seconds, 0)
+}
+
+/// Read a timestamp given either as RFC 3339 or as unix seconds
+pub fn parse(value: &str) -> Result<DateTime<Utc>> {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()) {
+        if let Ok(seconds) = value.parse::<i64>() {
+            if let Some(time) = NaiveDateTime::from_timestamp_opt(seconds, 0) {
+                return Ok(DateTime::from_utc(time, Utc));
+            }
+        }
+    }
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| NotesError::InvalidInput(format!("{} is not a timestamp", value)))
+}
+
+/// A fixed width form of `time`, so that sorting these strings sorts by time
+pub(crate) fn sort_key(time: &DateTime<Utc>) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S%.6fZ").to_string()
+}
+
+pub fn serialize<S: Serializer>(time: &DateTime<Utc>, serializer: S) -> Result<S::Ok, S::Error> {
+    match FORMAT.with(Cell::get) {
+        TimestampFormat::Rfc3339 => {
+            serializer.serialize_str(&time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+        }
+        TimestampFormat::EpochSeconds => serializer.serialize_str(&time.timestamp().to_string()),
+    }
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<DateTime<Utc>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse(&value).map_err(de::Error::custom)
+}
+
+/// The same, for timestamps that may be missing
+pub mod option {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        time: &Option<DateTime<Utc>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match time {
+            Some(time) => super::serialize(time, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<DateTime<Utc>>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(value) => super::parse(&value)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
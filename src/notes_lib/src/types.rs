@@ -1,8 +1,12 @@
+use crate::error::{NotesError, Result};
+use crate::timestamp;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Default, Deserialize, Queryable, Serialize)]
+#[derive(Debug, Clone, Deserialize, Queryable, Serialize)]
 /// A note represents a concept or idea
 pub struct Note {
     /// Unique id for this entry
@@ -15,15 +19,87 @@ pub struct Note {
     pub description: String,
     /// Tags assciated with this content
     pub tags: HashSet<Tag>,
-    pub create_time: String,
-    pub last_update_time: String,
-    pub delete_time: Option<String>,
+    #[serde(with = "timestamp")]
+    pub create_time: DateTime<Utc>,
+    #[serde(with = "timestamp")]
+    pub last_update_time: DateTime<Utc>,
+    /// When the note was archived, if it is
+    #[serde(with = "timestamp::option", default)]
+    pub delete_time: Option<DateTime<Utc>>,
+    /// Goes up by one every time the note changes. Served as the note's ETag
+    #[serde(default)]
+    pub version: u64,
+    /// Where the note is in its owner's workflow
+    #[serde(default)]
+    pub status: NoteStatus,
+}
+
+// chrono has no default time, so this one starts out at the unix epoch
+impl Default for Note {
+    fn default() -> Self {
+        Note {
+            uuid: Uuid::default(),
+            title: String::default(),
+            owner: String::default(),
+            description: String::default(),
+            tags: HashSet::default(),
+            create_time: timestamp::from_epoch_seconds(0),
+            last_update_time: timestamp::from_epoch_seconds(0),
+            delete_time: None,
+            version: 0,
+            status: NoteStatus::default(),
+        }
+    }
+}
+
+/// Where a note is in its owner's workflow, e.g. a book still to read or one that's been read
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum NoteStatus {
+    Wishlist,
+    InProgress,
+    Done,
+    Abandoned,
+}
+
+impl Default for NoteStatus {
+    fn default() -> Self {
+        NoteStatus::Wishlist
+    }
+}
+
+impl NoteStatus {
+    /// Name of the status, the way it is serialized and stored
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NoteStatus::Wishlist => "Wishlist",
+            NoteStatus::InProgress => "InProgress",
+            NoteStatus::Done => "Done",
+            NoteStatus::Abandoned => "Abandoned",
+        }
+    }
+}
+
+impl FromStr for NoteStatus {
+    type Err = NotesError;
+
+    fn from_str(status: &str) -> Result<NoteStatus> {
+        match status {
+            "Wishlist" => Ok(NoteStatus::Wishlist),
+            "InProgress" => Ok(NoteStatus::InProgress),
+            "Done" => Ok(NoteStatus::Done),
+            "Abandoned" => Ok(NoteStatus::Abandoned),
+            _ => Err(NotesError::InvalidInput(format!(
+                "{} is not a note status",
+                status
+            ))),
+        }
+    }
 }
 
 /// A list or collection of notes can be used to prioritize
 /// or collect various things into a group.
 /// Common examples are
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct List {
     pub uuid: Uuid,
     /// Vector of Uuid's to different Notes in the list
@@ -38,7 +114,8 @@ pub struct List {
 
 /// List of tags that can be associated with a Note
 /// This is explicitly encoded as a rust enum, because I don't
-/// want it to be easily added to. Keeping a limited set of tags is important
+/// want it to be easily added to. Keeping a limited set of tags is important.
+/// Anything else has to be defined by its owner first, see `TagDefinition`
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Tag {
     // Medium Based
@@ -57,6 +134,7 @@ pub enum Tag {
     // Genre based
     Career,
     Entertainment,
+    Food,
     Productivity,
 
     // Topic based
@@ -69,6 +147,518 @@ pub enum Tag {
     RecommendedBy(String),
     RemindsMeOf(String),
     Origin(String),
+    /// The owner has talked to this person about the note, usually the one who recommended it
+    TalkedTo(String),
+
+    // Owner defined, named after a `TagDefinition`
+    Custom(String),
+}
+
+/// The built-in tags without a payload, in the order they're declared
+pub const PLAIN_TAGS: &[Tag] = &[
+    Tag::Article,
+    Tag::Book,
+    Tag::Movie,
+    Tag::Music,
+    Tag::Series,
+    Tag::Podcast,
+    Tag::Recipe,
+    Tag::Restaurant,
+    Tag::Adventure,
+    Tag::VideoGame,
+    Tag::BoardGame,
+    Tag::Career,
+    Tag::Entertainment,
+    Tag::Food,
+    Tag::Productivity,
+    Tag::ArtificialIntelligence,
+    Tag::EffectiveAltruism,
+    Tag::SocialJustice,
+    Tag::Environmental,
+];
+
+impl Tag {
+    /// The broader tag this one implies, if any. Querying for a tag also finds
+    /// notes tagged with anything below it, e.g. `Entertainment` finds `Podcast`s
+    pub fn parent(&self) -> Option<Tag> {
+        match self {
+            Tag::Movie
+            | Tag::Music
+            | Tag::Series
+            | Tag::Podcast
+            | Tag::VideoGame
+            | Tag::BoardGame => Some(Tag::Entertainment),
+            Tag::Recipe | Tag::Restaurant => Some(Tag::Food),
+            _ => None,
+        }
+    }
+
+    /// The tags that directly imply this one
+    pub fn children(&self) -> Vec<Tag> {
+        PLAIN_TAGS
+            .iter()
+            .filter(|tag| tag.parent().as_ref() == Some(self))
+            .cloned()
+            .collect()
+    }
+
+    /// Every tag that implies this one, however indirectly
+    pub fn descendants(&self) -> Vec<Tag> {
+        self.children()
+            .into_iter()
+            .flat_map(|child| {
+                let below = child.descendants();
+                std::iter::once(child).chain(below)
+            })
+            .collect()
+    }
+
+    /// Name of this tag's variant, without its payload. e.g. `RecommendedBy`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Tag::Article => "Article",
+            Tag::Book => "Book",
+            Tag::Movie => "Movie",
+            Tag::Music => "Music",
+            Tag::Series => "Series",
+            Tag::Podcast => "Podcast",
+            Tag::Recipe => "Recipe",
+            Tag::Restaurant => "Restaurant",
+            Tag::Adventure => "Adventure",
+            Tag::VideoGame => "VideoGame",
+            Tag::BoardGame => "BoardGame",
+            Tag::Career => "Career",
+            Tag::Entertainment => "Entertainment",
+            Tag::Food => "Food",
+            Tag::Productivity => "Productivity",
+            Tag::ArtificialIntelligence => "ArtificialIntelligence",
+            Tag::EffectiveAltruism => "EffectiveAltruism",
+            Tag::SocialJustice => "SocialJustice",
+            Tag::Environmental => "Environmental",
+            Tag::RecommendedBy(_) => "RecommendedBy",
+            Tag::RemindsMeOf(_) => "RemindsMeOf",
+            Tag::Origin(_) => "Origin",
+            Tag::TalkedTo(_) => "TalkedTo",
+            Tag::Custom(_) => "Custom",
+        }
+    }
+
+    /// The payload of this tag, if its variant has one. e.g. `Alice` for `RecommendedBy("Alice")`
+    pub fn payload(&self) -> Option<&str> {
+        match self {
+            Tag::RecommendedBy(payload)
+            | Tag::RemindsMeOf(payload)
+            | Tag::Origin(payload)
+            | Tag::TalkedTo(payload)
+            | Tag::Custom(payload) => Some(payload),
+            _ => None,
+        }
+    }
+
+    /// The id of the `Person` this tag refers to, if it is about a person and refers to them by id
+    pub fn person_id(&self) -> Option<Uuid> {
+        match self {
+            Tag::RecommendedBy(payload) | Tag::RemindsMeOf(payload) | Tag::TalkedTo(payload) => {
+                payload
+                    .strip_prefix(PERSON_REFERENCE)
+                    .and_then(|id| Uuid::parse_str(id).ok())
+            }
+            _ => None,
+        }
+    }
+
+    /// Build a tag from its variant name and payload, if that makes a valid tag
+    pub fn from_parts(kind: &str, payload: Option<&str>) -> Option<Tag> {
+        let value = match payload {
+            None => serde_json::json!(kind),
+            Some(payload) => serde_json::json!({ kind: payload }),
+        };
+        serde_json::from_value(value).ok()
+    }
+}
+
+/// A tag an owner made up, which their notes can use as `Tag::Custom(name)`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TagDefinition {
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    /// How to show the tag, as `#rrggbb`
+    pub colour: String,
+}
+
+/// Tag payloads starting with this refer to a `Person` by id, e.g. `RecommendedBy("person:<uuid>")`
+pub const PERSON_REFERENCE: &str = "person:";
+
+/// Someone whose name turns up in the `RecommendedBy` and `RemindsMeOf` tags of an owner's notes.
+/// Tags can name them by their name or any of their aliases, or refer to them with
+/// `Person::reference`, which keeps pointing at them when they're renamed
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Person {
+    pub uuid: Uuid,
+    pub owner: String,
+    pub name: String,
+    /// Other ways their name has been written, e.g. `alice` or `Alice S.`
+    pub aliases: Vec<String>,
+    /// Anything worth remembering about them
+    pub description: String,
+}
+
+impl Person {
+    /// The tag payload that refers to this person
+    pub fn reference(&self) -> String {
+        format!("{}{}", PERSON_REFERENCE, self.uuid)
+    }
+
+    /// Every tag payload that means this person
+    pub fn payloads(&self) -> Vec<String> {
+        let mut payloads = vec![self.reference(), self.name.clone()];
+        payloads.extend(self.aliases.iter().cloned());
+        payloads
+    }
+
+    pub fn is_named_by(&self, payload: &str) -> bool {
+        payload == self.reference()
+            || payload == self.name
+            || self.aliases.iter().any(|alias| alias == payload)
+    }
+}
+
+/// A tag with the tags that imply it beneath it, for drawing the tag hierarchy
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TagTreeNode {
+    pub tag: Tag,
+    pub children: Vec<TagTreeNode>,
+}
+
+impl TagTreeNode {
+    fn new(tag: Tag) -> TagTreeNode {
+        let children = tag.children().into_iter().map(TagTreeNode::new).collect();
+        TagTreeNode { tag, children }
+    }
+
+    /// The built-in hierarchy, followed by `custom` tags, which don't have a place in it
+    pub fn tree(custom: Vec<Tag>) -> Vec<TagTreeNode> {
+        PLAIN_TAGS
+            .iter()
+            .filter(|tag| tag.parent().is_none())
+            .cloned()
+            .chain(custom)
+            .map(TagTreeNode::new)
+            .collect()
+    }
+}
+
+/// A boolean query over the tags of a note, such as
+/// `Book AND (Productivity OR Career) AND RecommendedBy("Alice") AND NOT Origin(*)`.
+/// See `tag_query::parse` for the syntax.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TagQuery {
+    /// The note has exactly this tag
+    Tag(Tag),
+    /// The note has a tag of this variant, whatever its payload. e.g. `Origin(*)`
+    AnyPayload(String),
+    And(Box<TagQuery>, Box<TagQuery>),
+    Or(Box<TagQuery>, Box<TagQuery>),
+    Not(Box<TagQuery>),
+}
+
+/// What a page of notes is ordered by. Ties are broken by uuid, so the order is total.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NoteSort {
+    CreateTime,
+    LastUpdateTime,
+    Title,
+}
+
+impl Default for NoteSort {
+    fn default() -> Self {
+        NoteSort::CreateTime
+    }
+}
+
+impl NoteSort {
+    /// The position of `note` in this order
+    pub fn key(&self, note: &Note) -> (String, Uuid) {
+        let key = match self {
+            NoteSort::CreateTime => timestamp::sort_key(&note.create_time),
+            NoteSort::LastUpdateTime => timestamp::sort_key(&note.last_update_time),
+            NoteSort::Title => note.title.clone(),
+        };
+        (key, note.uuid)
+    }
+
+    /// The column holding the key, in the SQL backends
+    pub(crate) fn column(&self) -> &'static str {
+        match self {
+            NoteSort::CreateTime => "create_time",
+            NoteSort::LastUpdateTime => "last_update_time",
+            NoteSort::Title => "title",
+        }
+    }
+}
+
+/// Where a page of notes left off, i.e. the position of its last note.
+/// Handed to clients as an opaque token.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NoteCursor {
+    pub sort: NoteSort,
+    pub key: String,
+    pub uuid: Uuid,
+}
+
+impl NoteCursor {
+    /// The cursor for the page after `note`
+    pub fn after(sort: NoteSort, note: &Note) -> NoteCursor {
+        let (key, uuid) = sort.key(note);
+        NoteCursor { sort, key, uuid }
+    }
+
+    /// Encode the cursor as a url safe token
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("Cursors always serialize");
+        json.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    pub fn decode(token: &str) -> Result<NoteCursor> {
+        let invalid = || NotesError::InvalidInput(format!("Invalid cursor {}", token));
+        if !token.is_ascii() || token.len() % 2 != 0 {
+            return Err(invalid());
+        }
+        let json = (0..token.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&token[i..i + 2], 16))
+            .collect::<std::result::Result<Vec<u8>, _>>()
+            .map_err(|_| invalid())?;
+        let cursor: NoteCursor = serde_json::from_slice(&json).map_err(|_| invalid())?;
+        if cursor.time().is_err() {
+            return Err(invalid());
+        }
+        Ok(cursor)
+    }
+
+    /// The key as a time, for cursors of pages sorted by time
+    pub fn time(&self) -> Result<Option<DateTime<Utc>>> {
+        match self.sort {
+            NoteSort::Title => Ok(None),
+            _ => timestamp::parse(&self.key).map(Some),
+        }
+    }
+}
+
+/// A past version of a note. One is recorded every time a note is updated
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NoteRevision {
+    pub note_id: Uuid,
+    /// Counts up from 1 for every note
+    pub revision: u32,
+    pub title: String,
+    pub description: String,
+    pub tags: HashSet<Tag>,
+    /// When this version of the note was saved
+    #[serde(with = "timestamp")]
+    pub last_update_time: DateTime<Utc>,
+}
+
+impl NoteRevision {
+    /// The version `note` is at right now, which becomes `revision` once it is updated
+    pub fn of(note: &Note, revision: u32) -> NoteRevision {
+        NoteRevision {
+            note_id: note.uuid,
+            revision,
+            title: note.title.clone(),
+            description: note.description.clone(),
+            tags: note.tags.clone(),
+            last_update_time: note.last_update_time,
+        }
+    }
+}
+
+/// How a field changed between two versions of a note
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct FieldChange {
+    pub from: String,
+    pub to: String,
+}
+
+/// A line of a line by line diff
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DiffLine {
+    Same(String),
+    Added(String),
+    Removed(String),
+}
+
+/// The differences between two versions of a note, see `history::diff`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct NoteDiff {
+    /// Missing if the title didn't change
+    pub title: Option<FieldChange>,
+    /// Every line of both descriptions, in order
+    pub description: Vec<DiffLine>,
+    pub tags_added: Vec<Tag>,
+    pub tags_removed: Vec<Tag>,
+}
+
+/// Whether archived notes belong in a page of notes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchivedNotes {
+    Exclude,
+    Include,
+    /// Only archived notes, i.e. the trash
+    Only,
+}
+
+impl Default for ArchivedNotes {
+    fn default() -> Self {
+        ArchivedNotes::Exclude
+    }
+}
+
+/// Which notes of an owner a store should return, and in what order
+#[derive(Debug, Clone, Default)]
+pub struct NotesQuery {
+    pub archived: ArchivedNotes,
+    /// Only notes with this status
+    pub status: Option<NoteStatus>,
+    /// Only notes whose tags match this query
+    pub tags: Option<TagQuery>,
+    pub sort: NoteSort,
+    /// Only notes created at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only notes created before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Only notes after this position, which must be in the order of `sort`
+    pub after: Option<NoteCursor>,
+    /// At most this many notes. All of them if `None`
+    pub limit: Option<usize>,
+}
+
+impl NotesQuery {
+    /// Whether `note` passes the filters of this query. It doesn't check the owner
+    pub fn includes(&self, note: &Note) -> bool {
+        let archived = note.delete_time.is_some();
+        let archived_match = match self.archived {
+            ArchivedNotes::Exclude => !archived,
+            ArchivedNotes::Include => true,
+            ArchivedNotes::Only => archived,
+        };
+        archived_match
+            && self.status.map_or(true, |status| note.status == status)
+            && self
+                .tags
+                .as_ref()
+                .map_or(true, |tags| tags.matches(&note.tags))
+            && self
+                .since
+                .as_ref()
+                .map_or(true, |since| note.create_time >= *since)
+            && self
+                .until
+                .as_ref()
+                .map_or(true, |until| note.create_time < *until)
+            && self.after.as_ref().map_or(true, |after| {
+                self.sort.key(note) > (after.key.clone(), after.uuid)
+            })
+    }
+}
+
+/// A page of notes, in the order that was asked for
+#[derive(Debug, Clone, Default)]
+pub struct NotesPage {
+    pub notes: Vec<Note>,
+    /// Where the next page starts, if there are more notes
+    pub next_cursor: Option<NoteCursor>,
+}
+
+impl NotesPage {
+    /// Build a page from ordered notes. Stores can fetch one note more than `limit`,
+    /// so that a cursor is only handed out when there really is a next page.
+    pub fn new(mut notes: Vec<Note>, sort: NoteSort, limit: Option<usize>) -> NotesPage {
+        let next_cursor = match limit {
+            Some(limit) if notes.len() > limit => {
+                notes.truncate(limit);
+                notes.last().map(|note| NoteCursor::after(sort, note))
+            }
+            _ => None,
+        };
+        NotesPage { notes, next_cursor }
+    }
+}
+
+/// How many notes of an owner have a tag of one variant, e.g. `Book (42)`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TagFacet {
+    /// Name of the variant, like `Tag::kind`
+    pub kind: String,
+    pub count: u64,
+    /// For variants with a payload, how many notes have each payload, e.g. `Alice (9)`.
+    /// A note recommended by two people counts once for the variant, but once for each of them
+    pub values: Vec<TagValueCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct TagValueCount {
+    pub value: String,
+    pub count: u64,
+}
+
+impl TagFacet {
+    /// Count the tags of notes, given the tags of each note
+    pub fn count<'a>(notes: impl IntoIterator<Item = &'a HashSet<Tag>>) -> Vec<TagFacet> {
+        let mut kinds: HashMap<String, u64> = HashMap::new();
+        let mut values: HashMap<String, HashMap<String, u64>> = HashMap::new();
+        for tags in notes {
+            let note_kinds: HashSet<&str> = tags.iter().map(Tag::kind).collect();
+            for kind in note_kinds {
+                *kinds.entry(kind.to_string()).or_default() += 1;
+            }
+            for tag in tags {
+                if let Some(payload) = tag.payload() {
+                    *values
+                        .entry(tag.kind().to_string())
+                        .or_default()
+                        .entry(payload.to_string())
+                        .or_default() += 1;
+                }
+            }
+        }
+        let facets = kinds
+            .into_iter()
+            .map(|(kind, count)| TagFacet {
+                values: values
+                    .remove(&kind)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(value, count)| TagValueCount { value, count })
+                    .collect(),
+                kind,
+                count,
+            })
+            .collect();
+        TagFacet::sorted(facets)
+    }
+
+    /// Most used first, with ties in name order. Values are ordered the same way
+    pub fn sorted(mut facets: Vec<TagFacet>) -> Vec<TagFacet> {
+        for facet in &mut facets {
+            facet.values.sort_by(|value, other| {
+                other
+                    .count
+                    .cmp(&value.count)
+                    .then(value.value.cmp(&other.value))
+            });
+        }
+        facets.sort_by(|facet, other| {
+            other
+                .count
+                .cmp(&facet.count)
+                .then(facet.kind.cmp(&other.kind))
+        });
+        facets
+    }
 }
 
 // API Interface
@@ -78,6 +668,9 @@ pub struct CreateNoteRequest {
     pub description: String,
     pub tags: Option<HashSet<Tag>>,
     pub owner: String,
+    /// `Wishlist` if not given
+    #[serde(default)]
+    pub status: Option<NoteStatus>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -93,11 +686,142 @@ pub struct GetNoteRequest {
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct GetNotesRequest {
     pub owner: String,
+    #[serde(default)]
+    pub archived: ArchivedNotes,
+    /// Only return notes with this status
+    pub status: Option<NoteStatus>,
+    /// Only return notes whose tags match this query
+    pub tags: Option<TagQuery>,
+    #[serde(default)]
+    pub sort: NoteSort,
+    /// Only return notes created at or after this time, in RFC 3339 or unix seconds
+    pub since: Option<String>,
+    /// Only return notes created before this time, in RFC 3339 or unix seconds
+    pub until: Option<String>,
+    /// Page size, 100 by default
+    pub limit: Option<usize>,
+    /// `next_cursor` of the previous page
+    pub cursor: Option<String>,
+}
+
+/// Query string of the owner notes endpoint,
+/// i.e. `?tags=Book AND NOT Origin(*)&sort=title&limit=20&cursor=...`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetNotesQuery {
+    /// Also return archived notes
+    pub include_archived: Option<bool>,
+    pub status: Option<NoteStatus>,
+    pub tags: Option<String>,
+    pub sort: Option<NoteSort>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct GetNotesResponse {
-    pub notes: HashMap<Uuid, Note>,
+    /// Notes in the order that was asked for
+    pub notes: Vec<Note>,
+    /// Pass this as `cursor` to get the next page. Missing on the last page
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SearchNotesRequest {
+    pub owner: String,
+    /// Words that must all appear in the title or description of a note
+    pub query: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SearchNotesResponse {
+    /// Matching notes, best match first
+    pub notes: Vec<Note>,
+}
+
+/// Query string of the search endpoint, i.e. `?q=deep+work`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct SearchQuery {
+    pub q: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetTagFacetsRequest {
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetTagFacetsResponse {
+    /// Every variant used by the owner's notes, most used first. Archived notes aren't counted
+    pub facets: Vec<TagFacet>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DefineTagRequest {
+    pub owner: String,
+    pub name: String,
+    pub description: String,
+    pub colour: String,
+}
+
+/// Body of the tag definition endpoint. The owner is in the path
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DefineTagBody {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub colour: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DefineTagResponse {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetTagDefinitionsRequest {
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetTagDefinitionsResponse {
+    /// Ordered by name
+    pub tags: Vec<TagDefinition>,
+}
+
+/// Replace the tag `from` with `to` on every note of `owner`, e.g. to tidy up
+/// `RecommendedBy("alice")` into `RecommendedBy("Alice")`
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RenameTagRequest {
+    pub owner: String,
+    pub from: Tag,
+    pub to: Tag,
+    /// Only find the notes that would be retagged, without changing them
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct RenameTagBody {
+    pub from: Tag,
+    pub to: Tag,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RenameTagResponse {
+    /// The notes that were retagged, or would be, as they were before. Ordered by title
+    pub notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetTagTreeRequest {
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetTagTreeResponse {
+    /// The top level tags, built-in ones first, then the owner's own in order of name
+    pub tags: Vec<TagTreeNode>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -108,6 +832,65 @@ pub struct ArchiveNoteRequest {
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct ArchiveNoteResponse {}
 
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetNoteHistoryRequest {
+    pub note_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetNoteHistoryResponse {
+    /// Past versions of the note, oldest first. The current version isn't included
+    pub revisions: Vec<NoteRevision>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DiffNoteRequest {
+    pub note_id: Uuid,
+    pub from: u32,
+    /// The current version of the note if missing
+    pub to: Option<u32>,
+}
+
+/// Query string of the diff endpoint, i.e. `?from=1&to=3`
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DiffQuery {
+    pub from: u32,
+    pub to: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DiffNoteResponse {
+    pub diff: NoteDiff,
+}
+
+/// Restore the title, description and tags of a past version of a note.
+/// The version being replaced is kept in the history, like with any other update
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RevertNoteRequest {
+    pub note_id: Uuid,
+    pub revision: u32,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RevertNoteResponse {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UnarchiveNoteRequest {
+    pub note_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UnarchiveNoteResponse {}
+
+/// Delete an archived note for good
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PurgeNoteRequest {
+    pub note_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PurgeNoteResponse {}
+
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct GetNoteResponse {
     pub note: Note,
@@ -120,13 +903,164 @@ pub struct UpdateNoteRequest {
     pub title: Option<String>,
     pub description: Option<String>,
     pub tags: Option<HashSet<Tag>>,
+    pub status: Option<NoteStatus>,
+    /// Only update the note if it is still at this version, see `If-Match`
+    #[serde(default)]
+    pub expected_version: Option<u64>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct UpdateNoteResponse {}
 
-#[derive(Deserialize, Serialize)]
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct FullList {
     pub list: List,
     pub notes_in_list: HashMap<Uuid, Note>,
 }
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CreateListRequest {
+    pub title: String,
+    pub description: String,
+    /// Notes to start the list with, in order
+    pub notes: Option<Vec<Uuid>>,
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CreateListResponse {
+    pub list_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetListsRequest {
+    pub owner: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetListsResponse {
+    pub lists: HashMap<Uuid, List>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetFullListRequest {
+    pub list_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetFullListResponse {
+    pub full_list: FullList,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UpdateListRequest {
+    /// list to update
+    pub list_id: Uuid,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Replaces the notes in the list, in order
+    pub notes: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UpdateListResponse {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DeleteListRequest {
+    pub list_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DeleteListResponse {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CreatePersonRequest {
+    pub owner: String,
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CreatePersonResponse {
+    pub person_id: Uuid,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UpdatePersonRequest {
+    /// person to update
+    pub person_id: Uuid,
+    /// A new name. The old one is kept as an alias, so tags that use it still find the person
+    pub name: Option<String>,
+    /// Replaces their aliases
+    pub aliases: Option<Vec<String>>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct UpdatePersonResponse {}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetPeopleRequest {
+    pub owner: String,
+}
+
+/// How often someone turns up in the tags of an owner's notes
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PersonSummary {
+    /// Unset for names that only turn up in tags, without a `Person` behind them
+    pub person_id: Option<Uuid>,
+    pub name: String,
+    /// How many notes they recommended
+    pub recommendations: u64,
+    /// How many notes remind the owner of them
+    pub reminders: u64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetPeopleResponse {
+    /// Whoever turns up most first, then by name. Archived notes aren't counted
+    pub people: Vec<PersonSummary>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetRecommendationsRequest {
+    pub person_id: Uuid,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GetRecommendationsResponse {
+    pub person: Person,
+    /// Notes tagged `RecommendedBy` the person under any of their names, by title
+    pub notes: Vec<Note>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetFollowupsRequest {
+    pub owner: String,
+}
+
+/// A `Done` note that someone recommended, who hasn't been told about it yet
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct Followup {
+    pub note: Note,
+    /// Everyone in the note's `RecommendedBy` tags without a `TalkedTo` tag for them, by name
+    pub people: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct GetFollowupsResponse {
+    /// Most recently updated first
+    pub followups: Vec<Followup>,
+}
+
+/// Body of every error response returned by the API
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ErrorResponse {
+    /// Short, stable name for the kind of error, e.g. `not_found`
+    pub error: String,
+    /// Human readable description of what went wrong
+    pub message: String,
+}
@@ -0,0 +1,15 @@
+use notes_lib::error::NotesError;
+
+#[test]
+fn notes_errors_survive_passing_through_anyhow() {
+    // Storage backends fail transactions with anyhow errors to roll them back
+    let conflict = anyhow::Error::from(NotesError::Conflict("Note moved on".to_string()));
+    match NotesError::from(conflict) {
+        NotesError::Conflict(message) => assert_eq!(message, "Note moved on"),
+        other => panic!("Expected a conflict, got {:?}", other),
+    }
+    match NotesError::from(anyhow::anyhow!("Query failed")) {
+        NotesError::Internal(err) => assert_eq!(err.to_string(), "Query failed"),
+        other => panic!("Expected an internal error, got {:?}", other),
+    }
+}
@@ -0,0 +1,144 @@
+use notes_lib::service::{NotesService, RequestHandler};
+use notes_lib::storage::MemoryNoteStore;
+use notes_lib::types::{
+    CreateNoteRequest, CreatePersonRequest, GetFollowupsRequest, NoteStatus, Tag, UpdateNoteRequest,
+};
+use std::thread;
+use std::time::Duration;
+use uuid::Uuid;
+
+fn create_note(
+    handler: &mut RequestHandler<MemoryNoteStore>,
+    title: &str,
+    status: NoteStatus,
+    tags: Vec<Tag>,
+) -> Uuid {
+    handler
+        .create_note(CreateNoteRequest {
+            title: title.to_string(),
+            owner: "followups".to_string(),
+            tags: Some(tags.into_iter().collect()),
+            status: Some(status),
+            ..CreateNoteRequest::default()
+        })
+        .unwrap()
+        .note_id
+}
+
+fn followups(handler: &RequestHandler<MemoryNoteStore>) -> Vec<(String, Vec<String>)> {
+    handler
+        .get_followups(GetFollowupsRequest {
+            owner: "followups".to_string(),
+        })
+        .unwrap()
+        .followups
+        .into_iter()
+        .map(|followup| (followup.note.title, followup.people))
+        .collect()
+}
+
+#[test]
+fn lists_done_recommendations_not_talked_about() {
+    let mut handler = RequestHandler::new(MemoryNoteStore::new());
+    let recommended_by = |name: &str| Tag::RecommendedBy(name.to_string());
+    let talked_to = |name: &str| Tag::TalkedTo(name.to_string());
+    create_note(
+        &mut handler,
+        "Deep Work",
+        NoteStatus::Wishlist,
+        vec![recommended_by("Alice")],
+    );
+    create_note(&mut handler, "Dune", NoteStatus::Done, vec![Tag::Book]);
+    create_note(
+        &mut handler,
+        "Arrival",
+        NoteStatus::Done,
+        vec![recommended_by("Alice"), talked_to("Alice")],
+    );
+    let hyperion = create_note(
+        &mut handler,
+        "Hyperion",
+        NoteStatus::Done,
+        vec![recommended_by("Alice"), recommended_by("Bob")],
+    );
+    let so_good = create_note(
+        &mut handler,
+        "So Good",
+        NoteStatus::InProgress,
+        vec![recommended_by("Bob")],
+    );
+    // Followups are ordered by update time, make sure it differs
+    thread::sleep(Duration::from_millis(1));
+    handler
+        .update_note(UpdateNoteRequest {
+            note_id: so_good,
+            status: Some(NoteStatus::Done),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+
+    assert_eq!(
+        followups(&handler),
+        vec![
+            ("So Good".to_string(), vec!["Bob".to_string()]),
+            (
+                "Hyperion".to_string(),
+                vec!["Alice".to_string(), "Bob".to_string()]
+            ),
+        ]
+    );
+
+    handler
+        .update_note(UpdateNoteRequest {
+            note_id: hyperion,
+            tags: Some(
+                vec![
+                    recommended_by("Alice"),
+                    recommended_by("Bob"),
+                    talked_to("Bob"),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+    assert_eq!(
+        followups(&handler)[0],
+        ("Hyperion".to_string(), vec!["Alice".to_string()])
+    );
+}
+
+#[test]
+fn any_name_of_a_person_counts_as_talking_to_them() {
+    let mut handler = RequestHandler::new(MemoryNoteStore::new());
+    let alice = handler
+        .create_person(CreatePersonRequest {
+            owner: "followups".to_string(),
+            name: "Alice".to_string(),
+            aliases: vec!["alice".to_string()],
+            description: String::new(),
+        })
+        .unwrap()
+        .person_id;
+    create_note(
+        &mut handler,
+        "Deep Work",
+        NoteStatus::Done,
+        vec![Tag::RecommendedBy("alice".to_string())],
+    );
+    create_note(
+        &mut handler,
+        "Arrival",
+        NoteStatus::Done,
+        vec![
+            Tag::RecommendedBy(format!("person:{}", alice)),
+            Tag::TalkedTo("Alice".to_string()),
+        ],
+    );
+
+    assert_eq!(
+        followups(&handler),
+        vec![("Deep Work".to_string(), vec!["Alice".to_string()])]
+    );
+}
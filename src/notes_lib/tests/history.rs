@@ -0,0 +1,125 @@
+use notes_lib::history::diff;
+use notes_lib::timestamp;
+use notes_lib::types::{DiffLine, FieldChange, NoteRevision, Tag};
+use uuid::Uuid;
+
+fn revision(revision: u32, title: &str, description: &str, tags: Vec<Tag>) -> NoteRevision {
+    NoteRevision {
+        note_id: Uuid::nil(),
+        revision,
+        title: title.to_string(),
+        description: description.to_string(),
+        tags: tags.into_iter().collect(),
+        last_update_time: timestamp::from_epoch_seconds(1609459200),
+    }
+}
+
+#[test]
+fn diffs_every_field() {
+    let from = revision(
+        1,
+        "Deep Work",
+        "Focus is rare\nFocus is valuable\nSo train it",
+        vec![Tag::Book, Tag::RecommendedBy("Alice".to_string())],
+    );
+    let to = revision(
+        2,
+        "Deep Work: Rules for Focused Success",
+        "Focus is rare\nSo train it\nEvery day",
+        vec![Tag::Book, Tag::Productivity, Tag::Career],
+    );
+
+    let diff = diff(&from, &to);
+
+    assert_eq!(
+        diff.title,
+        Some(FieldChange {
+            from: "Deep Work".to_string(),
+            to: "Deep Work: Rules for Focused Success".to_string(),
+        })
+    );
+    assert_eq!(
+        diff.description,
+        vec![
+            DiffLine::Same("Focus is rare".to_string()),
+            DiffLine::Removed("Focus is valuable".to_string()),
+            DiffLine::Same("So train it".to_string()),
+            DiffLine::Added("Every day".to_string()),
+        ]
+    );
+    assert_eq!(diff.tags_added, vec![Tag::Career, Tag::Productivity]);
+    assert_eq!(
+        diff.tags_removed,
+        vec![Tag::RecommendedBy("Alice".to_string())]
+    );
+}
+
+#[test]
+fn unchanged_revisions_have_an_empty_diff() {
+    let note = revision(1, "Dune", "Spice\nWorms", vec![Tag::Book]);
+
+    let diff = diff(&note, &revision(2, "Dune", "Spice\nWorms", vec![Tag::Book]));
+
+    assert_eq!(diff.title, None);
+    assert!(diff
+        .description
+        .iter()
+        .all(|line| matches!(line, DiffLine::Same(_))));
+    assert!(diff.tags_added.is_empty());
+    assert!(diff.tags_removed.is_empty());
+}
+
+/// The description a diff goes from, and the one it goes to
+fn sides(lines: &[DiffLine]) -> (Vec<&str>, Vec<&str>) {
+    let (mut from, mut to) = (Vec::new(), Vec::new());
+    for line in lines {
+        match line {
+            DiffLine::Same(line) => {
+                from.push(line.as_str());
+                to.push(line.as_str());
+            }
+            DiffLine::Removed(line) => from.push(line.as_str()),
+            DiffLine::Added(line) => to.push(line.as_str()),
+        }
+    }
+    (from, to)
+}
+
+#[test]
+fn large_descriptions_diff_without_a_huge_table() {
+    // Mostly blank lines, which would take a table of billions of entries to line up
+    let from_description = "a\n\n".repeat(50_000);
+    let to_description = "\nb\n".repeat(50_000);
+    let from = revision(1, "Dune", &from_description, vec![]);
+    let to = revision(2, "Dune", &to_description, vec![]);
+
+    let diff = diff(&from, &to);
+
+    let (from_lines, to_lines) = sides(&diff.description);
+    assert_eq!(from_lines, from_description.lines().collect::<Vec<_>>());
+    assert_eq!(to_lines, to_description.lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn small_changes_to_large_descriptions_stay_small() {
+    let lines: Vec<String> = (0..100_000).map(|line| format!("Line {}", line)).collect();
+    let mut changed = lines.clone();
+    changed[50_000] = "Changed".to_string();
+    let from = revision(1, "Dune", &lines.join("\n"), vec![]);
+    let to = revision(2, "Dune", &changed.join("\n"), vec![]);
+
+    let diff = diff(&from, &to);
+
+    let changes: Vec<&DiffLine> = diff
+        .description
+        .iter()
+        .filter(|line| !matches!(line, DiffLine::Same(_)))
+        .collect();
+    assert_eq!(
+        changes,
+        vec![
+            &DiffLine::Removed("Line 50000".to_string()),
+            &DiffLine::Added("Changed".to_string()),
+        ]
+    );
+}
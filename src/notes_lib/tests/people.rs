@@ -0,0 +1,176 @@
+use notes_lib::error::{NotesError, Result};
+use notes_lib::service::{NotesService, RequestHandler};
+use notes_lib::storage::MemoryNoteStore;
+use notes_lib::types::{
+    ArchiveNoteRequest, CreateNoteRequest, CreatePersonRequest, GetPeopleRequest,
+    GetRecommendationsRequest, PersonSummary, Tag, UpdatePersonRequest,
+};
+use uuid::Uuid;
+
+fn create_note(handler: &mut RequestHandler<MemoryNoteStore>, title: &str, tags: Vec<Tag>) -> Uuid {
+    handler
+        .create_note(CreateNoteRequest {
+            title: title.to_string(),
+            owner: "people".to_string(),
+            tags: Some(tags.into_iter().collect()),
+            ..CreateNoteRequest::default()
+        })
+        .unwrap()
+        .note_id
+}
+
+fn create_person(
+    handler: &mut RequestHandler<MemoryNoteStore>,
+    name: &str,
+    aliases: Vec<&str>,
+) -> Result<Uuid> {
+    let response = handler.create_person(CreatePersonRequest {
+        owner: "people".to_string(),
+        name: name.to_string(),
+        aliases: aliases.into_iter().map(String::from).collect(),
+        description: String::new(),
+    })?;
+    Ok(response.person_id)
+}
+
+fn summary(
+    person_id: Option<Uuid>,
+    name: &str,
+    recommendations: u64,
+    reminders: u64,
+) -> PersonSummary {
+    PersonSummary {
+        person_id,
+        name: name.to_string(),
+        recommendations,
+        reminders,
+    }
+}
+
+#[test]
+fn lists_everyone_in_tags() {
+    let mut handler = RequestHandler::new(MemoryNoteStore::new());
+    let alice = create_person(&mut handler, "Alice", vec!["alice", "Alice S."]).unwrap();
+    let recommended_by = |name: &str| Tag::RecommendedBy(name.to_string());
+    create_note(&mut handler, "Deep Work", vec![recommended_by("alice")]);
+    create_note(&mut handler, "Dune", vec![recommended_by("Alice S.")]);
+    create_note(
+        &mut handler,
+        "Arrival",
+        vec![recommended_by(&format!("person:{}", alice))],
+    );
+    create_note(
+        &mut handler,
+        "Hyperion",
+        vec![Tag::RemindsMeOf("Alice".to_string()), recommended_by("Bob")],
+    );
+    let archived = create_note(&mut handler, "So Good", vec![recommended_by("Bob")]);
+    handler
+        .archive_note(ArchiveNoteRequest { note_id: archived })
+        .unwrap();
+
+    let people = handler
+        .get_people(GetPeopleRequest {
+            owner: "people".to_string(),
+        })
+        .unwrap()
+        .people;
+    assert_eq!(
+        people,
+        vec![
+            summary(Some(alice), "Alice", 3, 1),
+            summary(None, "Bob", 1, 0),
+        ]
+    );
+
+    let recommendations = handler
+        .get_recommendations(GetRecommendationsRequest { person_id: alice })
+        .unwrap();
+    let titles: Vec<&str> = recommendations
+        .notes
+        .iter()
+        .map(|note| note.title.as_str())
+        .collect();
+    assert_eq!(titles, vec!["Arrival", "Deep Work", "Dune"]);
+}
+
+#[test]
+fn counts_each_note_once_per_person() {
+    let mut handler = RequestHandler::new(MemoryNoteStore::new());
+    let bob = create_person(&mut handler, "Bob", vec!["Robert"]).unwrap();
+    let recommended_by = |name: &str| Tag::RecommendedBy(name.to_string());
+    create_note(
+        &mut handler,
+        "Dune",
+        vec![
+            recommended_by("Bob"),
+            recommended_by("Robert"),
+            Tag::RemindsMeOf("Bob".to_string()),
+        ],
+    );
+    create_note(
+        &mut handler,
+        "Arrival",
+        vec![recommended_by("Carol"), recommended_by("Robert")],
+    );
+
+    let people = handler
+        .get_people(GetPeopleRequest {
+            owner: "people".to_string(),
+        })
+        .unwrap()
+        .people;
+    assert_eq!(
+        people,
+        vec![
+            summary(Some(bob), "Bob", 2, 1),
+            summary(None, "Carol", 1, 0),
+        ]
+    );
+}
+
+#[test]
+fn renaming_keeps_old_name_as_alias() {
+    let mut handler = RequestHandler::new(MemoryNoteStore::new());
+    let alice = create_person(&mut handler, "Alcie", vec![]).unwrap();
+    create_note(
+        &mut handler,
+        "Deep Work",
+        vec![Tag::RecommendedBy("Alcie".to_string())],
+    );
+    handler
+        .update_person(UpdatePersonRequest {
+            person_id: alice,
+            name: Some("Alice".to_string()),
+            ..UpdatePersonRequest::default()
+        })
+        .unwrap();
+
+    let recommendations = handler
+        .get_recommendations(GetRecommendationsRequest { person_id: alice })
+        .unwrap();
+    assert_eq!(recommendations.person.name, "Alice");
+    assert_eq!(recommendations.person.aliases, vec!["Alcie"]);
+    assert_eq!(recommendations.notes.len(), 1);
+}
+
+#[test]
+fn rejects_ambiguous_people_and_unknown_references() {
+    let mut handler = RequestHandler::new(MemoryNoteStore::new());
+    create_person(&mut handler, "Alice", vec!["Al"]).unwrap();
+    for (name, aliases) in [("Al", vec![]), ("Bob", vec!["Alice"]), (" ", vec![])] {
+        assert!(matches!(
+            create_person(&mut handler, name, aliases),
+            Err(NotesError::InvalidInput(_))
+        ));
+    }
+
+    let unknown = Tag::RecommendedBy(format!("person:{}", Uuid::new_v4()));
+    let created = handler.create_note(CreateNoteRequest {
+        title: "Dune".to_string(),
+        owner: "people".to_string(),
+        tags: Some(vec![unknown].into_iter().collect()),
+        ..CreateNoteRequest::default()
+    });
+    assert!(matches!(created, Err(NotesError::InvalidInput(_))));
+}
@@ -0,0 +1,48 @@
+use notes_lib::routes::build_warp_routes;
+use notes_lib::service::RequestHandler;
+use notes_lib::storage::MemoryNoteStore;
+use notes_lib::timestamp::TimestampFormat;
+use notes_lib::types::ErrorResponse;
+use warp::filters::BoxedFilter;
+use warp::http::StatusCode;
+use warp::Reply;
+
+fn routes() -> BoxedFilter<(impl Reply,)> {
+    build_warp_routes(
+        RequestHandler::new(MemoryNoteStore::new()),
+        TimestampFormat::Rfc3339,
+    )
+}
+
+#[tokio::test]
+async fn invalid_bodies_are_bad_requests() {
+    let routes = routes();
+
+    let response = warp::test::request()
+        .method("POST")
+        .path("/notes")
+        .header("content-type", "application/json")
+        .body(r#"{"title": 42}"#)
+        .reply(&routes)
+        .await;
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body: ErrorResponse = serde_json::from_slice(response.body()).unwrap();
+    assert_eq!(body.error, "invalid_input");
+}
+
+#[tokio::test]
+async fn routes_match_whole_paths() {
+    let routes = routes();
+
+    let search = |path: &str| warp::test::request().path(path).reply(&routes);
+
+    assert_eq!(
+        search("/notes/alice/search?q=dune").await.status(),
+        StatusCode::OK
+    );
+    assert_eq!(
+        search("/notes/alice/search/anything?q=dune").await.status(),
+        StatusCode::NOT_FOUND
+    );
+}
@@ -0,0 +1,267 @@
+//! Runs the conformance suite against every `NoteStore` so the backends can't drift apart.
+//! The Postgres suite only runs when `NOTES_TEST_DATABASE_URL` points at a migrated database.
+use notes_lib::storage::{FileNoteStore, MemoryNoteStore, NoteStore, PsqlNoteStore};
+use notes_lib::timestamp;
+use notes_lib::types::{
+    ArchiveNoteRequest, ArchivedNotes, List, Note, NoteSort, NoteStatus, NotesQuery, Person, Tag,
+    TagDefinition, UpdateNoteRequest,
+};
+use std::{env, fs};
+use uuid::Uuid;
+
+fn with_memory_store(check: fn(MemoryNoteStore)) {
+    check(MemoryNoteStore::new());
+}
+
+fn with_psql_store(check: fn(PsqlNoteStore)) {
+    match env::var("NOTES_TEST_DATABASE_URL") {
+        Ok(url) => check(PsqlNoteStore::new(&url)),
+        Err(_) => eprintln!("Skipping, NOTES_TEST_DATABASE_URL is not set"),
+    }
+}
+
+#[cfg(feature = "sqlite")]
+fn with_sqlite_store(check: fn(notes_lib::storage::SqliteNoteStore)) {
+    check(notes_lib::storage::SqliteNoteStore::new(":memory:"));
+}
+
+fn with_file_store(check: fn(FileNoteStore)) {
+    let data_dir = env::temp_dir().join(format!("notes-test-{}", Uuid::new_v4()));
+    // Compact often, so that compaction is exercised along the way
+    check(FileNoteStore::with_compaction(&data_dir, 5));
+    fs::remove_dir_all(&data_dir).unwrap();
+}
+
+mod memory_conformance {
+    notes_lib::conformance_tests!(super::with_memory_store);
+}
+
+mod psql_conformance {
+    notes_lib::conformance_tests!(super::with_psql_store);
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite_conformance {
+    notes_lib::conformance_tests!(super::with_sqlite_store);
+}
+
+mod file_conformance {
+    notes_lib::conformance_tests!(super::with_file_store);
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn sqlite_reads_more_notes_than_one_query_can_take() {
+    let mut store = notes_lib::storage::SqliteNoteStore::new(":memory:");
+    let owner = "many".to_string();
+    for index in 0..1200 {
+        store
+            .create_note(Note {
+                uuid: Uuid::new_v4(),
+                title: format!("Note {:04}", index),
+                owner: owner.clone(),
+                tags: vec![Tag::Origin(index.to_string())].into_iter().collect(),
+                delete_time: Some(timestamp::from_epoch_seconds(0)),
+                ..Note::default()
+            })
+            .unwrap();
+    }
+
+    let query = NotesQuery {
+        archived: ArchivedNotes::Only,
+        sort: NoteSort::Title,
+        ..NotesQuery::default()
+    };
+    let notes = store.get_notes(owner, query).unwrap().notes;
+    assert_eq!(notes.len(), 1200);
+    for (index, note) in notes.iter().enumerate() {
+        assert_eq!(
+            note.tags,
+            vec![Tag::Origin(index.to_string())].into_iter().collect()
+        );
+    }
+    let purged = store
+        .purge_archived_before(timestamp::from_epoch_seconds(1), false)
+        .unwrap();
+    assert_eq!(purged.len(), 1200);
+}
+
+#[test]
+fn file_store_survives_reopen() {
+    let data_dir = env::temp_dir().join(format!("notes-test-{}", Uuid::new_v4()));
+    let mut store = FileNoteStore::with_compaction(&data_dir, 3);
+    let note = Note {
+        uuid: Uuid::new_v4(),
+        title: "Deep Work".to_string(),
+        owner: "reopen".to_string(),
+        ..Note::default()
+    };
+    store.create_note(note.clone()).unwrap();
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            title: Some("Dune".to_string()),
+            status: Some(NoteStatus::Done),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+    store
+        .archive_note(ArchiveNoteRequest { note_id: note.uuid })
+        .unwrap();
+    let list = List {
+        uuid: Uuid::new_v4(),
+        notes: vec![note.uuid],
+        title: "To Read".to_string(),
+        owner: "reopen".to_string(),
+        description: String::new(),
+    };
+    store.store_list(list.clone()).unwrap();
+    let deleted_list = List {
+        uuid: Uuid::new_v4(),
+        ..list.clone()
+    };
+    store.store_list(deleted_list.clone()).unwrap();
+    store.delete_list(deleted_list.uuid).unwrap();
+    let purged = Note {
+        uuid: Uuid::new_v4(),
+        ..note.clone()
+    };
+    store.create_note(purged.clone()).unwrap();
+    store
+        .store_list(List {
+            notes: vec![purged.uuid, note.uuid],
+            ..list.clone()
+        })
+        .unwrap();
+    store.purge_note(purged.uuid).unwrap();
+    let museum = TagDefinition {
+        owner: "reopen".to_string(),
+        name: "Museum".to_string(),
+        description: "Places to visit".to_string(),
+        colour: "#1e90ff".to_string(),
+    };
+    store.store_tag_definition(museum.clone()).unwrap();
+    let alice = Person {
+        uuid: Uuid::new_v4(),
+        owner: "reopen".to_string(),
+        name: "Alice".to_string(),
+        aliases: vec!["alice".to_string()],
+        description: String::new(),
+    };
+    store.store_person(alice.clone()).unwrap();
+    drop(store);
+
+    let reopened = FileNoteStore::new(&data_dir);
+    let stored = reopened.get_note(note.uuid).unwrap();
+    assert_eq!(stored.title, "Dune");
+    assert_eq!(stored.status, NoteStatus::Done);
+    assert!(stored.delete_time.is_some());
+    let history = reopened.get_note_history(note.uuid).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].title, "Deep Work");
+    let lists = reopened.get_lists("reopen".to_string()).unwrap();
+    assert_eq!(lists.len(), 1);
+    assert_eq!(lists[&list.uuid].notes, vec![note.uuid]);
+    assert!(reopened.get_note(purged.uuid).is_err());
+    assert_eq!(
+        reopened.get_tag_definitions("reopen".to_string()).unwrap(),
+        vec![museum]
+    );
+    assert_eq!(reopened.get_person(alice.uuid).unwrap(), alice);
+    fs::remove_dir_all(&data_dir).unwrap();
+}
+
+#[test]
+fn file_store_drops_torn_batches_whole() {
+    let data_dir = env::temp_dir().join(format!("notes-test-{}", Uuid::new_v4()));
+    let mut store = FileNoteStore::new(&data_dir);
+    let mut uuids = Vec::new();
+    for title in ["Deep Work", "Dune"] {
+        let note = Note {
+            uuid: Uuid::new_v4(),
+            title: title.to_string(),
+            owner: "torn".to_string(),
+            tags: vec![Tag::Book].into_iter().collect(),
+            ..Note::default()
+        };
+        uuids.push(note.uuid);
+        store.create_note(note).unwrap();
+    }
+    store
+        .rename_tag("torn".to_string(), Tag::Book, Tag::Article, false)
+        .unwrap();
+    drop(store);
+
+    // Tear the rename, as a crash halfway through writing it would
+    let log_path = data_dir.join("notes.jsonl");
+    let log = fs::read_to_string(&log_path).unwrap();
+    fs::write(&log_path, &log[..log.len() - 20]).unwrap();
+
+    let reopened = FileNoteStore::new(&data_dir);
+    for uuid in uuids {
+        let note = reopened.get_note(uuid).unwrap();
+        assert_eq!(note.tags, vec![Tag::Book].into_iter().collect());
+        assert!(reopened.get_note_history(uuid).unwrap().is_empty());
+    }
+    fs::remove_dir_all(&data_dir).unwrap();
+}
+
+#[test]
+fn memory_snapshot_round_trip() {
+    let snapshot_path = env::temp_dir().join(format!("notes-test-{}.json", Uuid::new_v4()));
+    // No snapshot yet means starting empty
+    let mut store = MemoryNoteStore::load_snapshot(&snapshot_path).unwrap();
+    let note = Note {
+        uuid: Uuid::new_v4(),
+        title: "Deep Work".to_string(),
+        owner: "snapshot".to_string(),
+        ..Note::default()
+    };
+    store.create_note(note.clone()).unwrap();
+    store
+        .update_note(UpdateNoteRequest {
+            note_id: note.uuid,
+            title: Some("Dune".to_string()),
+            ..UpdateNoteRequest::default()
+        })
+        .unwrap();
+    let list = List {
+        uuid: Uuid::new_v4(),
+        notes: vec![note.uuid],
+        title: "To Read".to_string(),
+        owner: "snapshot".to_string(),
+        description: String::new(),
+    };
+    store.store_list(list.clone()).unwrap();
+    let museum = TagDefinition {
+        owner: "snapshot".to_string(),
+        name: "Museum".to_string(),
+        description: "Places to visit".to_string(),
+        colour: "#1e90ff".to_string(),
+    };
+    store.store_tag_definition(museum.clone()).unwrap();
+    let alice = Person {
+        uuid: Uuid::new_v4(),
+        owner: "snapshot".to_string(),
+        name: "Alice".to_string(),
+        aliases: vec!["alice".to_string()],
+        description: String::new(),
+    };
+    store.store_person(alice.clone()).unwrap();
+    store.dump_snapshot(&snapshot_path).unwrap();
+
+    let restored = MemoryNoteStore::load_snapshot(&snapshot_path).unwrap();
+    assert_eq!(restored.get_note(note.uuid).unwrap().title, "Dune");
+    let history = restored.get_note_history(note.uuid).unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].title, "Deep Work");
+    assert_eq!(restored.get_list(list.uuid).unwrap().notes, vec![note.uuid]);
+    assert_eq!(
+        restored
+            .get_tag_definitions("snapshot".to_string())
+            .unwrap(),
+        vec![museum]
+    );
+    assert_eq!(restored.get_person(alice.uuid).unwrap(), alice);
+    fs::remove_file(&snapshot_path).unwrap();
+}
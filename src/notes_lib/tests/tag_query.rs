@@ -0,0 +1,192 @@
+use notes_lib::error::NotesError;
+use notes_lib::tag_query::{parse, MAX_DEPTH};
+use notes_lib::types::{Tag, TagQuery, TagTreeNode, PLAIN_TAGS};
+use std::collections::HashSet;
+
+fn tag(tag: Tag) -> Box<TagQuery> {
+    Box::new(TagQuery::Tag(tag))
+}
+
+#[test]
+fn parses_precedence() {
+    // NOT binds tighter than AND, which binds tighter than OR
+    assert_eq!(
+        parse("not Book and Movie or Podcast").unwrap(),
+        TagQuery::Or(
+            Box::new(TagQuery::And(
+                Box::new(TagQuery::Not(tag(Tag::Book))),
+                tag(Tag::Movie)
+            )),
+            tag(Tag::Podcast)
+        )
+    );
+    assert_eq!(
+        parse("Book AND (Productivity OR Career)").unwrap(),
+        TagQuery::And(
+            tag(Tag::Book),
+            Box::new(TagQuery::Or(tag(Tag::Productivity), tag(Tag::Career)))
+        )
+    );
+}
+
+#[test]
+fn parses_payloads() {
+    assert_eq!(
+        parse(r#"RecommendedBy("Alice \"Al\" Smith") AND NOT Origin(*)"#).unwrap(),
+        TagQuery::And(
+            tag(Tag::RecommendedBy("Alice \"Al\" Smith".to_string())),
+            Box::new(TagQuery::Not(Box::new(TagQuery::AnyPayload(
+                "Origin".to_string()
+            ))))
+        )
+    );
+}
+
+#[test]
+fn rejects_invalid_queries() {
+    for query in &[
+        "",
+        "Book AND",
+        "(Book",
+        "Book)",
+        "Book Movie",
+        "Novel",
+        "book",
+        "Book(*)",
+        "RecommendedBy",
+        "RecommendedBy(Alice)",
+        r#"RecommendedBy("Alice"#,
+        "Book & Movie",
+    ] {
+        match parse(query) {
+            Err(NotesError::InvalidInput(_)) => {}
+            other => panic!("Expected {:?} to be invalid, got {:?}", query, other),
+        }
+    }
+}
+
+#[test]
+fn rejects_deeply_nested_queries() {
+    let chain = |op: &str, count: usize| vec!["Book"; count].join(op);
+    for query in &[
+        "(".repeat(10_000),
+        format!("{}Book", "NOT ".repeat(10_000)),
+        chain(" AND ", 10_000),
+        chain(" OR ", 10_000),
+    ] {
+        match parse(query) {
+            Err(NotesError::InvalidInput(_)) => {}
+            other => panic!(
+                "Expected a deeply nested query to be invalid, got {:?}",
+                other
+            ),
+        }
+    }
+
+    let nested = format!("{}Book{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+    assert_eq!(parse(&nested).unwrap(), TagQuery::Tag(Tag::Book));
+    assert!(parse(&chain(" AND ", MAX_DEPTH)).is_ok());
+    assert!(parse(&chain(" AND ", MAX_DEPTH + 1)).is_err());
+}
+
+#[test]
+fn displays_as_parseable_query() {
+    for query in &[
+        r#"Book AND (Productivity OR Career) AND RecommendedBy("Alice") AND NOT Origin(*)"#,
+        r#"NOT (Movie OR RemindsMeOf("a \"quoted\\ name"))"#,
+    ] {
+        let parsed = parse(query).unwrap();
+        assert_eq!(parse(&parsed.to_string()).unwrap(), parsed);
+    }
+}
+
+#[test]
+fn every_tag_displays_as_itself() {
+    let with_payloads = vec![
+        Tag::RecommendedBy("Alice".to_string()),
+        Tag::RemindsMeOf("Alice".to_string()),
+        Tag::Origin("Alice".to_string()),
+        Tag::TalkedTo("Alice".to_string()),
+        Tag::Custom("Alice".to_string()),
+    ];
+    for tag in PLAIN_TAGS.iter().cloned().chain(with_payloads) {
+        assert_eq!(
+            Tag::from_parts(tag.kind(), tag.payload()),
+            Some(tag.clone())
+        );
+        let query = TagQuery::Tag(tag);
+        assert_eq!(parse(&query.to_string()).unwrap(), query);
+    }
+}
+
+#[test]
+fn matches_tags() {
+    let tags: HashSet<Tag> = vec![
+        Tag::Book,
+        Tag::Productivity,
+        Tag::RecommendedBy("Alice".to_string()),
+    ]
+    .into_iter()
+    .collect();
+    let matches = |query: &str| query.parse::<TagQuery>().unwrap().matches(&tags);
+    assert!(matches(
+        r#"Book AND (Productivity OR Career) AND RecommendedBy("Alice") AND NOT Origin(*)"#
+    ));
+    assert!(matches("RecommendedBy(*)"));
+    assert!(!matches(r#"RecommendedBy("Bob")"#));
+    assert!(!matches("Book AND NOT Productivity"));
+    assert!(matches("Movie OR Book"));
+}
+
+#[test]
+fn expands_parent_tags() {
+    let podcast: HashSet<Tag> = vec![Tag::Podcast].into_iter().collect();
+    let recipe: HashSet<Tag> = vec![Tag::Recipe, Tag::Book].into_iter().collect();
+    let matches = |query: &str, tags: &HashSet<Tag>| {
+        query.parse::<TagQuery>().unwrap().expand().matches(tags)
+    };
+    assert!(matches("Entertainment", &podcast));
+    assert!(!matches("Entertainment", &recipe));
+    assert!(matches("Food AND Book", &recipe));
+    assert!(!matches("NOT Entertainment", &podcast));
+    // Children don't imply their siblings or match their parent's tag on its own
+    assert!(!matches("Music", &podcast));
+    assert!(!"Entertainment"
+        .parse::<TagQuery>()
+        .unwrap()
+        .matches(&podcast));
+}
+
+#[test]
+fn builds_tag_tree() {
+    let tree = TagTreeNode::tree(vec![Tag::Custom("Museum".to_string())]);
+    let entertainment = tree
+        .iter()
+        .find(|node| node.tag == Tag::Entertainment)
+        .unwrap();
+    let children: Vec<&Tag> = entertainment
+        .children
+        .iter()
+        .map(|node| &node.tag)
+        .collect();
+    assert_eq!(
+        children,
+        vec![
+            &Tag::Movie,
+            &Tag::Music,
+            &Tag::Series,
+            &Tag::Podcast,
+            &Tag::VideoGame,
+            &Tag::BoardGame
+        ]
+    );
+    // Children only show up under their parent
+    assert!(tree.iter().all(|node| node.tag != Tag::Podcast));
+    assert_eq!(
+        tree.last().unwrap(),
+        &TagTreeNode {
+            tag: Tag::Custom("Museum".to_string()),
+            children: vec![],
+        }
+    );
+}
@@ -0,0 +1,56 @@
+use notes_lib::error::NotesError;
+use notes_lib::timestamp::{self, TimestampFormat};
+use notes_lib::types::Note;
+
+#[test]
+fn parses_both_formats() {
+    let new_year = timestamp::from_epoch_seconds(1609459200);
+    assert_eq!(timestamp::parse("1609459200").unwrap(), new_year);
+    assert_eq!(timestamp::parse("2021-01-01T00:00:00Z").unwrap(), new_year);
+    assert_eq!(
+        timestamp::parse("2021-01-01T01:00:00+01:00").unwrap(),
+        new_year
+    );
+    assert!(matches!(
+        timestamp::parse("yesterday"),
+        Err(NotesError::InvalidInput(_))
+    ));
+}
+
+#[test]
+fn writes_legacy_format_on_request() {
+    let note = Note {
+        create_time: timestamp::from_epoch_seconds(1609459200),
+        last_update_time: timestamp::from_epoch_seconds(1609459260),
+        ..Note::default()
+    };
+    let json = serde_json::to_value(&note).unwrap();
+    assert_eq!(json["create_time"], "2021-01-01T00:00:00Z");
+    assert_eq!(json["delete_time"], serde_json::Value::Null);
+
+    let json = timestamp::with_format(TimestampFormat::EpochSeconds, || {
+        serde_json::to_value(&note).unwrap()
+    });
+    assert_eq!(json["create_time"], "1609459200");
+    assert_eq!(json["last_update_time"], "1609459260");
+    // Both read back to the same note
+    let read: Note = serde_json::from_value(json).unwrap();
+    assert_eq!(read.create_time, note.create_time);
+}
+
+#[test]
+fn restores_format_after_a_panic() {
+    let note = Note {
+        create_time: timestamp::from_epoch_seconds(1609459200),
+        ..Note::default()
+    };
+    let panicked = std::panic::catch_unwind(|| {
+        timestamp::with_format(TimestampFormat::EpochSeconds, || {
+            panic!("Serializing failed")
+        })
+    });
+    assert!(panicked.is_err());
+
+    let json = serde_json::to_value(&note).unwrap();
+    assert_eq!(json["create_time"], "2021-01-01T00:00:00Z");
+}